@@ -0,0 +1,172 @@
+use crate::commands;
+use crate::helpers;
+use crate::room;
+use std::fs;
+
+/// Reads every alias out of the text of an aliases file, one per
+/// line, as 'name = commands'. Lines starting with '#' are comments.
+
+pub fn parse(data: &str) -> Vec<(String, String)> {
+    data.lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| {
+            let mut parts = l.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(body)) if !name.trim().is_empty() =>
+                    Some((name.trim().to_ascii_lowercase(),
+                          body.trim().to_string())),
+                _                                                  => None,
+            }
+        })
+        .collect()
+}
+
+/// Loads the aliases from the file at the given path.
+
+pub fn load(path: &str) -> Vec<(String, String)> {
+    parse(&helpers::read_data(path))
+}
+
+/// Writes the aliases back out to the file at the given path,
+/// so they're still around the next time the game starts.
+
+pub fn save(path: &str, aliases: &[(String, String)]) {
+    let mut data = "# Command aliases, as 'name = commands'.\n\
+                    # Set them in the game with 'alias name = commands'.\n"
+                   .to_string();
+    for (name, body) in aliases.iter() {
+        data.push_str(&format!("{} = {}\n", name, body));
+    }
+    fs::write(path, data).expect("Unable to write aliases file");
+}
+
+/// Swaps an alias at the start of a command for the commands it
+/// stands for. Anything typed after the alias is tacked on the
+/// end, so with 'x = look', 'x wall' means 'look wall'.
+
+pub fn expand(aliases: &[(String, String)], input: &str) -> String {
+    let mut words = input.splitn(2, char::is_whitespace);
+    let first = words.next().unwrap_or("").to_ascii_lowercase();
+    let rest = words.next().unwrap_or("").trim();
+    match aliases.iter().find(|a| a.0 == first) {
+        Some((_, body)) if rest.is_empty() => body.clone(),
+        Some((_, body))                    => format!("{} {}", body, rest),
+        None                               => input.to_string(),
+    }
+}
+
+/// Expands a command into the commands it comes to, following
+/// aliases that stand for other aliases. An alias showing up again
+/// inside its own expansion is left alone, so aliases that stand
+/// for each other can't go on expanding forever.
+
+pub fn expand_all(aliases: &[(String, String)], input: &str) -> Vec<String> {
+    expand_from(aliases, input, &[])
+}
+
+/// Does the work for expand_all(), given the aliases
+/// already being expanded on the way to this command.
+
+fn expand_from(aliases: &[(String, String)], input: &str,
+               expanding: &[String]) -> Vec<String> {
+    let first = input.split_whitespace()
+                     .next()
+                     .unwrap_or("")
+                     .to_ascii_lowercase();
+    if expanding.contains(&first) || !aliases.iter().any(|a| a.0 == first) {
+        return vec![input.to_string()]
+    }
+    let mut expanding = expanding.to_vec();
+    expanding.push(first);
+    commands::split(&expand(aliases, input))
+        .iter()
+        .flat_map(|c| expand_from(aliases, c, &expanding))
+        .collect()
+}
+
+/// Lists the aliases, or sets one up with 'alias name = commands'.
+/// 'alias name =' with nothing after it gets rid of that alias.
+/// Aliases can't take over a command or direction's own name,
+/// or use their own name in what they stand for.
+///
+/// Returns the aliases as they stand afterwards.
+
+pub fn alias_cmd(aliases: &[(String, String)], rest: &str) -> Vec<(String, String)> {
+    let mut aliases = aliases.to_vec();
+    if rest.is_empty() {
+        println!("\n\nAliases:");
+        for (name, body) in aliases.iter() {
+            println!("  {} = {}", name, body);
+        }
+        return aliases
+    }
+    let mut parts = rest.splitn(2, '=');
+    let name = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+    let body = match parts.next() {
+        Some(body) => body.trim().to_string(),
+        None       => {
+            println!("\n\nTo make an alias, enter 'alias name = commands'.");
+            return aliases
+        }
+    };
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        println!("\n\nAn alias has to be a single word.");
+    } else if commands::canonical(&name).is_some()
+              || room::direction(&name).is_some() {
+        println!("\n\n'{}' is already a command.", name);
+    } else if commands::split(&body).iter()
+                                     .filter_map(|c| c.split_whitespace().next())
+                                     .any(|w| w.eq_ignore_ascii_case(&name)) {
+        println!("\n\n'{}' can't stand for itself.", name);
+    } else if body.is_empty() {
+        aliases.retain(|a| a.0 != name);
+        println!("\n\n'{}' isn't an alias anymore.", name);
+    } else {
+        println!("\n\n'{}' now means '{}'.", name, body);
+        match aliases.iter_mut().find(|a| a.0 == name) {
+            Some(alias) => alias.1 = body,
+            None        => aliases.push((name, body)),
+        }
+    }
+    aliases
+}
+
+#[test]
+fn expand_test() {
+    let aliases = parse("# Built in.\nt = talk\nsneak = n then look\nbroken\n");
+    assert_eq!(2, aliases.len());
+    assert_eq!("talk", expand(&aliases, "T"));
+    assert_eq!("n then look wall", expand(&aliases, "sneak wall"));
+    assert_eq!("talk to carl", expand(&aliases, "talk to carl"));
+}
+
+#[test]
+fn expand_all_test() {
+    let aliases = parse("l = look
+z = wait
+peek = l wall
+                         tour = peek then z
+ping = pong
+pong = ping
+");
+    // Aliases can stand for other aliases, however far down.
+    assert_eq!(vec!["look wall", "wait"], expand_all(&aliases, "tour"));
+    assert_eq!(vec!["look wall table"], expand_all(&aliases, "peek table"));
+    // But not forever.
+    assert_eq!(vec!["ping"], expand_all(&aliases, "ping"));
+}
+
+#[test]
+fn alias_cmd_test() {
+    let aliases = parse("t = talk\n");
+    let aliases = alias_cmd(&aliases, "lw = look wall");
+    assert_eq!("look wall", expand(&aliases, "lw"));
+    // Commands keep their own names, whatever the case.
+    assert_eq!(aliases, alias_cmd(&aliases, "go = wait"));
+    assert_eq!(aliases, alias_cmd(&aliases, "Go = wait"));
+    // An alias that runs itself would never stop.
+    assert_eq!(aliases, alias_cmd(&aliases, "zz = wait then ZZ"));
+    let aliases = alias_cmd(&aliases, "t =");
+    assert_eq!(vec![("lw".to_string(), "look wall".to_string())], aliases);
+}
@@ -0,0 +1,124 @@
+use crate::npcs::Npc;
+use crate::room;
+use crate::state::State;
+
+/// Draws a map of every room the player has visited, laid out by
+/// the rooms' grid spots with north at the top. The player shows up
+/// as '@', and anyone the player has seen as the first letter of
+/// their name. Passages show up as '-', '|', '/' and '\', or as
+/// '#' while they're locked. Secret passages stay hidden until
+/// they're found, and so does every room the player hasn't been to,
+/// though the passages leading into them are drawn.
+
+pub fn draw(gstate: &State, npcs: &[Npc]) -> String {
+    let map = &gstate.map;
+    let visited: Vec<(usize, (i32, i32))> =
+        gstate.visited.iter()
+                      .filter_map(|&r| map.coords(r).map(|c| (r, c)))
+                      .collect();
+    if visited.is_empty() {
+        return String::new()
+    }
+    // Every passage worth drawing, as the spots at either end
+    // of it and whether it's still locked.
+    let mut passages = Vec::new();
+    for &(r, from) in visited.iter() {
+        for exit in map.exits_from(r) {
+            let open = exit.lock.is_empty() || gstate.has_opened(&exit.lock);
+            if !open && room::is_secret(&exit.lock) {
+                continue;
+            }
+            if let Some(to) = map.coords(exit.to) {
+                passages.push((from, to, open));
+            }
+        }
+    }
+    let spots: Vec<(i32, i32)> = visited.iter()
+                                        .map(|v| v.1)
+                                        .chain(passages.iter().map(|p| p.1))
+                                        .collect();
+    let min_x = spots.iter().map(|s| s.0).min().unwrap();
+    let max_x = spots.iter().map(|s| s.0).max().unwrap();
+    let min_y = spots.iter().map(|s| s.1).min().unwrap();
+    let max_y = spots.iter().map(|s| s.1).max().unwrap();
+    // Each room takes up four columns and two rows, counting
+    // the space for the passages east and north of it.
+    let width = ((max_x - min_x + 1) * 4) as usize;
+    let height = ((max_y - min_y + 1) * 2) as usize;
+    let mut canvas = vec![vec![' '; width]; height];
+    let col = |x: i32| ((x - min_x) * 4) as usize;
+    let row = |y: i32| ((max_y - y) * 2 + 1) as usize;
+    for &(r, (x, y)) in visited.iter() {
+        let mark = if r == gstate.curr_room {
+            '@'
+        } else {
+            npcs.iter()
+                .find(|n| n.is_in(r))
+                .and_then(|n| n.name.chars().next())
+                .unwrap_or(' ')
+        };
+        canvas[row(y)][col(x)] = '[';
+        canvas[row(y)][col(x) + 1] = mark;
+        canvas[row(y)][col(x) + 2] = ']';
+    }
+    for &(from, to, open) in passages.iter() {
+        // Only passages between neighboring spots can be drawn.
+        match (to.0 - from.0, to.1 - from.1) {
+            (1, 0)  => canvas[row(from.1)][col(from.0) + 3]
+                           = if open { '-' } else { '#' },
+            (-1, 0) => canvas[row(to.1)][col(to.0) + 3]
+                           = if open { '-' } else { '#' },
+            (0, 1)  => canvas[row(from.1) - 1][col(from.0) + 1]
+                           = if open { '|' } else { '#' },
+            (0, -1) => canvas[row(to.1) - 1][col(to.0) + 1]
+                           = if open { '|' } else { '#' },
+            (1, 1)  => canvas[row(from.1) - 1][col(from.0) + 3]
+                           = if open { '/' } else { '#' },
+            (-1, -1) => canvas[row(to.1) - 1][col(to.0) + 3]
+                           = if open { '/' } else { '#' },
+            (-1, 1) => canvas[row(from.1) - 1][col(to.0) + 3]
+                           = if open { '\\' } else { '#' },
+            (1, -1) => canvas[row(to.1) - 1][col(from.0) + 3]
+                           = if open { '\\' } else { '#' },
+            _       => {},
+        }
+    }
+    canvas.iter()
+          .map(|line| line.iter().collect::<String>().trim_end().to_string())
+          .skip_while(|line| line.is_empty())
+          .collect::<Vec<String>>()
+          .join("\n")
+}
+
+/// Displays the map of the rooms the player has visited.
+/// Doesn't take a turn.
+
+pub fn map_cmd(gstate: &State, npcs: &[Npc]) {
+    println!("\n\n{}", draw(gstate, npcs));
+    println!("\n@ you   - | / \\ passage   # locked   \
+              Other letters are people you've seen.");
+}
+
+#[test]
+fn draw_test() {
+    let mut gstate = State::new(1,false,true,false,false
+                               ,false,false,false,false);
+    gstate.visited = vec![0, 1];
+    let carl = Npc::new("Carl".to_string(), String::new(), String::new(),
+                        String::new(), String::new(), 2);
+    // The secret room stays hidden, the smelly cell is locked,
+    // and the corridor north hasn't been visited yet.
+    assert_eq!(" |\n[@]#\n |\n[ ]", draw(&gstate, &[carl]));
+}
+
+#[test]
+fn draw_test_gone() {
+    let mut gstate = State::new(5,true,true,true,true
+                               ,true,true,true,false);
+    gstate.visited = vec![5, 6];
+    let blimpo = Npc::new("Blimpo".to_string(), String::new(), String::new(),
+                          String::new(), String::new(), 6);
+    assert!(draw(&gstate, &[blimpo.receive_item()]).contains("[B]"));
+    // Once he's gone through the final door, he's off the map.
+    assert!(!draw(&gstate, &[blimpo.receive_item().leave()]).contains("[B]"));
+}
@@ -0,0 +1,228 @@
+use crate::guards;
+use crate::rng::Rng;
+use crate::state::{State, MAX_HEALTH};
+use crate::status;
+
+// The player's fighting stats.
+const PLAYER_ATTACK: u32 = 2;
+const PLAYER_DEFENSE: u32 = 1;
+
+/// Represents an enemy that will fight the player
+/// whenever the two of them share a room.
+
+pub struct Enemy {
+    pub name: String,
+    pub location: usize,
+    pub health: u32,
+    pub attack: u32,
+    pub defense: u32,
+}
+
+/// Implementation of Enemy struct.
+
+impl Enemy {
+
+    /// Create a new Enemy at full health.
+
+    pub fn new(name: String, location: usize, health: u32,
+               attack: u32, defense: u32) -> Enemy {
+        Enemy { name, location, health, attack, defense }
+    }
+
+    /// Whether the enemy can still fight.
+
+    pub fn is_alive(&self) -> bool {
+        self.health > 0
+    }
+
+    /// Take a string representing a saved Enemy and create an Enemy
+    /// object from its contents.
+
+    pub fn deserialize(data: &str) -> Enemy {
+        // First entry always \n, so we skip it.
+        let mut entries = data.lines().skip(1);
+        Enemy {
+            name: entries.next().unwrap().to_string(),
+            location: entries.next().unwrap().parse().unwrap(),
+            health: entries.next().unwrap().parse().unwrap(),
+            attack: entries.next().unwrap().parse().unwrap(),
+            defense: entries.next().unwrap().parse().unwrap(),
+        }
+    }
+
+    /// Convert an Enemy object into a String representation for saving.
+
+    pub fn serialize(&self) -> String {
+        // The individual Enemy delimiter is '%'
+        format!("\n%\n{}\n{}\n{}\n{}\n{}",
+                self.name, self.location, self.health,
+                self.attack, self.defense)
+    }
+}
+
+/// Creates a Vec of predetermined, hardcoded Enemy objects.
+
+pub fn generate_enemies() -> Vec<Enemy> {
+    vec![
+        Enemy::new("rat".to_string(), 8, 6, 2, 0),
+        Enemy::new("spider".to_string(), 11, 3, 1, 0),
+    ]
+}
+
+/// Loads each enemy's data and returns them in a Vec.
+
+pub fn deserialize(data: &str) -> Vec<Enemy> {
+    // Enemy object data in the save file is separated by '%'s
+    // First will always be newline, so skip it.
+    data.split('%')
+        .skip(1)
+        .filter(|e| !e.is_empty())
+        .map(Enemy::deserialize)
+        .collect()
+}
+
+/// Converts each Enemy into a String representation for saving.
+
+pub fn serialize(enemies: &[Enemy]) -> String {
+    let mut data = String::new();
+    for enemy in enemies { data.push_str(&enemy.serialize()) };
+    data
+}
+
+/// The best weapon the player is currently carrying,
+/// as a (name, attack bonus) pair.
+
+pub fn weapon(gstate: &State) -> (&'static str, u32) {
+    // The nail goes to Blimpo, and the broom to Carl.
+    if gstate.took_nail && !gstate.final_room_unlocked {
+        ("jagged nail", 3)
+    } else if gstate.took_broom && !gstate.helped_carl {
+        ("broom", 2)
+    } else {
+        ("fists", 0)
+    }
+}
+
+/// Rolls how much damage one hit does, which is
+/// never less than a single point.
+
+fn roll_damage(rng: &mut Rng, attack: u32, defense: u32) -> u32 {
+    (attack + rng.range(0, 3)).saturating_sub(defense).max(1)
+}
+
+/// Lets the player attack an enemy in the same room.
+/// Attacking takes a turn, and the enemy fights back
+/// afterwards, in enemy_turn(), if it's still standing.
+/// Winning a fight leaves the player exhausted.
+///
+/// Returns the updated game state and enemies.
+
+pub fn attack_cmd(mut gstate: State, mut enemies: Vec<Enemy>,
+                  target: &str) -> (State, Vec<Enemy>) {
+    let found = enemies.iter().position(|e| e.location == gstate.curr_room
+                                            && e.is_alive()
+                                            && (target.is_empty()
+                                                || e.name == target));
+    match found {
+        Some(i) => {
+            let (weapon_name, bonus) = weapon(&gstate);
+            // Fighting hurt takes something out of every swing.
+            let attack = if status::has(&gstate, status::INJURED) {
+                (PLAYER_ATTACK + bonus).saturating_sub(2)
+            } else {
+                PLAYER_ATTACK + bonus
+            };
+            let enemy = &mut enemies[i];
+            let damage = roll_damage(&mut gstate.rng, attack, enemy.defense);
+            enemy.health = enemy.health.saturating_sub(damage);
+            println!("\n\nYou hit the {} with your {} for {} damage.",
+                     enemy.name, weapon_name, damage);
+            if !enemy.is_alive() {
+                println!("The {} goes still.", enemy.name);
+                println!("You're left gasping for breath.");
+                return (status::add(gstate.tick(), status::EXHAUSTED),
+                        enemies)
+            }
+            (gstate.tick(), enemies)
+        }
+        None    => {
+            if target.is_empty() {
+                println!("\n\nThere's nothing here to fight.");
+            } else {
+                println!("\n\nThere's no {} here to fight.", target);
+            }
+            (gstate, enemies)
+        }
+    }
+}
+
+/// Called whenever a turn passes. Every living enemy in the
+/// player's room attacks them. The only way out is to win
+/// or to flee into another room.
+///
+/// Being badly hurt leaves the player injured, and if the
+/// player is defeated, they wake up back in their cell.
+
+pub fn enemy_turn(enemies: &[Enemy], mut gstate: State) -> State {
+    let mut health = gstate.health;
+    let room = gstate.curr_room;
+    for enemy in enemies.iter()
+                        .filter(|e| e.location == room && e.is_alive()) {
+        let damage = roll_damage(&mut gstate.rng, enemy.attack,
+                                 PLAYER_DEFENSE);
+        health = health.saturating_sub(damage);
+        println!("\n\nThe {} attacks you for {} damage! ({} of {} health left)",
+                 enemy.name, damage, health, MAX_HEALTH);
+    }
+    if health == 0 {
+        println!("\n\nEverything goes dark...\n\n\
+                  You wake up on the floor of your cell,\n\
+                  bruised, but in one piece.");
+        return State { health: MAX_HEALTH,
+                       effects: Vec::new(),
+                       ..guards::captured(gstate) }
+    }
+    // Dropping to half health or less leaves a mark.
+    if health <= MAX_HEALTH / 2 && health < gstate.health
+       && !status::has(&gstate, status::INJURED) {
+        println!("You're hurt, badly.");
+        return status::add(State { health, ..gstate }, status::INJURED)
+    }
+    State { health, ..gstate }
+}
+
+#[test]
+fn enemies_round_trip_test() {
+    let enemies = deserialize(&serialize(&generate_enemies()));
+    assert_eq!(2, enemies.len());
+    assert_eq!("rat", enemies[0].name);
+    assert_eq!(8, enemies[0].location);
+    assert_eq!(6, enemies[0].health);
+}
+
+#[test]
+fn attack_cmd_test() {
+    let mut gstate = State::empty();
+    gstate.curr_room = 8;
+    gstate.rng = Rng::new(7);
+    // The same seed always fights the same fight.
+    let (a, rat_a) = attack_cmd(gstate.clone(), generate_enemies(), "rat");
+    let (_, rat_b) = attack_cmd(gstate.clone(), generate_enemies(), "rat");
+    assert_eq!(rat_a[0].health, rat_b[0].health);
+    assert!(rat_a[0].health < 6);
+    assert_eq!(1, a.turns);
+    // Nobody to fight in the cell.
+    gstate.curr_room = 0;
+    let (b, _) = attack_cmd(gstate, generate_enemies(), "rat");
+    assert_eq!(0, b.turns);
+}
+
+#[test]
+fn enemy_turn_defeat_test() {
+    let mut gstate = State::empty();
+    gstate.curr_room = 8;
+    gstate.health = 1;
+    let after = enemy_turn(&generate_enemies(), gstate);
+    assert_eq!(0, after.curr_room);
+    assert_eq!(MAX_HEALTH, after.health);
+}
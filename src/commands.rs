@@ -0,0 +1,275 @@
+use crate::room;
+
+/// Every kind of command the game understands, other than
+/// directions and the aliases the player has set up. What the
+/// player types is read into one of these through COMMANDS, and
+/// main() has to handle every one of them.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Go, Travel, Back, Look, Talk, Attack,
+    Wait, Score, Status, Exits, Map, StatusLine,
+    Verbose, Brief, SuperBrief, Again, Oops, Alias,
+    Help, Hint, Quit,
+}
+
+/// Every command the game understands, as (what it is, its name,
+/// other names for it, how to use it, what it does). Other names
+/// are turned into the command's own name before anything else
+/// looks at the command, and both the list of commands in 'help'
+/// and what main() does with a command are worked out from this,
+/// so they always agree.
+
+pub const COMMANDS: [(Command, &str, &[&str], &str, &str); 21] = [
+    (Command::Go, "go", &["move", "walk"],
+     "go [direction]", "Move in a given direction."),
+    (Command::Travel, "travel", &[],
+     "travel [room]", "Walk to a room you've visited."),
+    (Command::Back, "back", &["return"],
+     "back", "Go back the way you came."),
+    (Command::Look, "look", &["examine"],
+     "look [object]", "Look around, or at something."),
+    (Command::Talk, "talk", &["speak"],
+     "talk", "Speak to whoever's in the room."),
+    (Command::Attack, "attack", &["fight", "hit"],
+     "attack [enemy]", "Fight an enemy in the room."),
+    (Command::Wait, "wait", &[],
+     "wait", "Let a turn pass."),
+    (Command::Score, "score", &["time"],
+     "score", "Show the turn, time, and your score."),
+    (Command::Status, "status", &["health"],
+     "status", "Show your health and how you feel."),
+    (Command::Exits, "exits", &[],
+     "exits", "List the ways out of the room."),
+    (Command::Map, "map", &[],
+     "map", "Show a map of the rooms you've seen."),
+    (Command::StatusLine, "statusline", &[],
+     "statusline [on/off]", "Show where you are in the prompt."),
+    (Command::Verbose, "verbose", &[],
+     "verbose", "Always describe rooms in full."),
+    (Command::Brief, "brief", &[],
+     "brief", "Describe rooms in full on the first visit."),
+    (Command::SuperBrief, "superbrief", &[],
+     "superbrief", "Only ever show room names."),
+    (Command::Again, "again", &[],
+     "again", "Do your last command again."),
+    (Command::Oops, "oops", &[],
+     "oops [word]", "Fix a word the game didn't get."),
+    (Command::Alias, "alias", &[],
+     "alias [name = commands]", "List your shortcuts, or make one."),
+    (Command::Help, "help", &[],
+     "help [command]", "Show this list, or help with a command."),
+    (Command::Hint, "hint", &[],
+     "hint", "Get a nudge toward what to do next."),
+    (Command::Quit, "quit", &["exit"],
+     "quit", "Save the game and quit."),
+];
+
+/// The name of the command the given word stands for,
+/// if it's a command or another name for one.
+
+pub fn canonical(word: &str) -> Option<&'static str> {
+    COMMANDS.iter()
+            .find(|c| c.1 == word || c.2.contains(&word))
+            .map(|c| c.1)
+}
+
+/// The command the given word stands for, if it's
+/// a command or another name for one.
+
+pub fn lookup(word: &str) -> Option<Command> {
+    COMMANDS.iter()
+            .find(|c| c.1 == word || c.2.contains(&word))
+            .map(|c| c.0)
+}
+
+/// Every word the game takes as a command,
+/// including other names for commands.
+
+pub fn verbs() -> Vec<&'static str> {
+    COMMANDS.iter()
+            .flat_map(|c| Some(c.1).into_iter().chain(c.2.iter().cloned()))
+            .collect()
+}
+
+/// Breaks a line of input up into the commands it holds, which
+/// can be separated by 'then', '.' or ','. For example,
+/// 'look wall, in then look table' holds three commands.
+/// A line setting up an alias is all one command, since
+/// what the alias stands for can hold several itself.
+
+pub fn split(line: &str) -> Vec<String> {
+    let first = line.split_whitespace().next().unwrap_or("");
+    if first.eq_ignore_ascii_case("alias") {
+        return vec![line.trim().to_string()]
+    }
+    let mut commands = Vec::new();
+    for part in line.split(&['.', ','][..]) {
+        let mut words: Vec<&str> = Vec::new();
+        for word in part.split_whitespace() {
+            if word.eq_ignore_ascii_case("then") {
+                commands.push(words.join(" "));
+                words.clear();
+            } else {
+                words.push(word);
+            }
+        }
+        commands.push(words.join(" "));
+    }
+    commands.retain(|c| !c.is_empty());
+    commands
+}
+
+/// Works out which command the player means when they type
+/// 'again', which repeats their last command, or
+/// 'oops <word>', which swaps the word the game didn't understand
+/// in their last command for the one given. Any other
+/// command means just what it says.
+///
+/// Returns the command to carry out, or what to tell the
+/// player if there's nothing to repeat or fix.
+
+pub fn recall(input: &str, last: &str,
+              mistake: &Option<(String, String)>) -> Result<String, String> {
+    let mut words = input.split_whitespace();
+    match words.next().map(|w| w.to_ascii_lowercase()).as_deref() {
+        Some("again") if last.is_empty() =>
+            Err("There's nothing to do again.".to_string()),
+        Some("again") =>
+            Ok(last.to_string()),
+        Some("oops") => match (words.next(), mistake) {
+            (None, _)                       =>
+                Err("Oops what?".to_string()),
+            (Some(_), None)                 =>
+                Err("There's nothing to fix.".to_string()),
+            (Some(word), Some((line, bad))) =>
+                Ok(fix(line, bad, word)),
+        },
+        _            =>
+            Ok(input.to_string()),
+    }
+}
+
+/// Swaps the first use of one word in a command for another.
+
+fn fix(line: &str, bad: &str, word: &str) -> String {
+    let mut fixed = false;
+    line.split_whitespace()
+        .map(|w| if !fixed && w.eq_ignore_ascii_case(bad) {
+            fixed = true;
+            word
+        } else {
+            w
+        })
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// How many single-letter changes it takes to turn one word into
+/// another, counting swapping two letters that are next to each
+/// other as one change, since that's such an easy slip to make.
+
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1)
+                                       .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Finds the word the player most likely meant to type, out of the
+/// words that would have made sense. Longer words are allowed more
+/// slips, and words too short to guess at aren't guessed at.
+
+fn closest<'a>(word: &str, words: &[&'a str]) -> Option<&'a str> {
+    let allowed = if word.len() < 3 { 0 } else if word.len() < 6 { 1 } else { 2 };
+    words.iter()
+         .filter(|w| w.len() > 1)
+         .map(|&w| (distance(word, w), w))
+         .filter(|&(d, _)| d > 0 && d <= allowed)
+         .min_by_key(|&(d, _)| d)
+         .map(|(_, w)| w)
+}
+
+/// Suggests a fix for a command the game didn't understand, by
+/// swapping the word it tripped over for the closest one that
+/// would have made sense there.
+
+pub fn suggest(line: &str, bad: &str, words: &[&str]) -> Option<String> {
+    closest(&bad.to_ascii_lowercase(), words).map(|w| fix(line, bad, w))
+}
+
+/// Whether the given command (by its own name), when it works, always takes a turn.
+/// One that doesn't take a turn has failed, such as walking into
+/// a wall or looking at something that isn't there.
+
+pub fn takes_turn(cmd: &str, arg: &str) -> bool {
+    match cmd {
+        "go" | "back" | "talk" | "attack" => true,
+        "look"                            => !arg.is_empty(),
+        dir                               => room::direction(dir).is_some(),
+    }
+}
+
+#[test]
+fn suggest_test() {
+    let dirs = ["north", "south", "up"];
+    assert_eq!(Some("go north".to_string()), suggest("go nroth", "nroth", &dirs));
+    assert_eq!(Some("look table".to_string()),
+               suggest("look tabel", "tabel", &["table", "wall"]));
+    assert_eq!(Some("talk".to_string()), suggest("tlak", "tlak", &verbs()));
+    // Nothing close enough, or too short to tell.
+    assert_eq!(None, suggest("go sideways", "sideways", &dirs));
+    assert_eq!(None, suggest("go uo", "uo", &dirs));
+}
+
+#[test]
+fn canonical_test() {
+    assert_eq!(Some("look"), canonical("examine"));
+    assert_eq!(Some("quit"), canonical("quit"));
+    assert_eq!(None, canonical("north"));
+    assert!(verbs().contains(&"hit"));
+    // Every name a command goes by is read as that command.
+    for (command, name, others, _, _) in COMMANDS.iter() {
+        assert_eq!(Some(*command), lookup(name));
+        for other in others.iter() {
+            assert_eq!(Some(*command), lookup(other));
+        }
+    }
+}
+
+#[test]
+fn split_test() {
+    assert_eq!(vec!["look wall", "in", "look table"],
+               split("look wall, in then look table."));
+    assert_eq!(vec!["n"], split(" n ,, then "));
+    assert!(split("").is_empty());
+    assert_eq!(vec!["alias sneak = n then look"],
+               split("alias sneak = n then look"));
+}
+
+#[test]
+fn recall_test() {
+    assert!(recall("again", "", &None).is_err());
+    assert_eq!(Ok("go north".to_string()), recall("again", "go north", &None));
+    let mistake = Some(("go nroth".to_string(), "nroth".to_string()));
+    assert_eq!(Ok("go north".to_string()),
+               recall("oops north", "go nroth", &mistake));
+    assert!(recall("oops", "go nroth", &mistake).is_err());
+    assert_eq!(Ok("look".to_string()), recall("look", "go north", &mistake));
+}
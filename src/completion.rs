@@ -0,0 +1,204 @@
+use crate::aliases;
+use crate::combat::Enemy;
+use crate::commands;
+use crate::npcs::Npc;
+use crate::room;
+use crate::state::State;
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::{Context, Helper};
+use std::borrow::Cow;
+
+/// Represents what the player could type next, as things stand
+/// in the game. Tab completes the word being typed, and the rest
+/// of a word that can only be one thing shows up in grey.
+///
+/// It's refreshed from the game state before every prompt.
+
+#[derive(Default)]
+pub struct InputHelper {
+    verbs: Vec<String>,
+    aliases: Vec<(String, String)>,
+    directions: Vec<String>,
+    nouns: Vec<String>,
+    people: Vec<String>,
+    enemies: Vec<String>,
+}
+
+/// The implementation of the InputHelper struct.
+
+impl InputHelper {
+
+    /// Catches up with the game: the commands and aliases there
+    /// are, the ways out of the player's room, what they could
+    /// look at, and who's in there with them.
+
+    pub fn refresh(&mut self, gstate: &State, npcs: &[Npc], enemies: &[Enemy],
+                   nouns: &[&str], aliases: &[(String, String)]) {
+        let room = gstate.curr_room;
+        let lower = |words: Vec<&str>| -> Vec<String> {
+            words.iter().map(|w| w.to_ascii_lowercase()).collect()
+        };
+        self.verbs = lower(commands::verbs().into_iter()
+                                            .chain(aliases.iter()
+                                                          .map(|a| a.0.as_str()))
+                                            .collect());
+        self.aliases = aliases.to_vec();
+        self.directions = lower(gstate.map.exits_from(room)
+                                          .iter()
+                                          .filter(|e| !room::is_secret(&e.lock)
+                                                      || gstate.has_opened(&e.lock))
+                                          .map(|e| e.dir.as_str())
+                                          .collect());
+        self.nouns = lower(nouns.to_vec());
+        self.people = lower(npcs.iter()
+                                .filter(|n| n.is_in(room))
+                                .map(|n| n.name.as_str())
+                                .collect());
+        self.enemies = lower(enemies.iter()
+                                    .filter(|e| e.location == room && e.is_alive())
+                                    .map(|e| e.name.as_str())
+                                    .collect());
+    }
+
+    /// The words that make sense after the given words of a command.
+
+    fn choices(&self, before: &[&str]) -> Vec<&str> {
+        let expanded = match before.first() {
+            Some(first) => aliases::expand_all(&self.aliases, first),
+            None        => {
+                let mut words = strs(&self.verbs);
+                words.extend(strs(&self.directions));
+                return words
+            }
+        };
+        let verb = expanded.first()
+                           .and_then(|c| c.split_whitespace().next())
+                           .unwrap_or("");
+        let verb = commands::canonical(verb).unwrap_or("");
+        let mut words = match (verb, &before[1..]) {
+            ("go", [])         => vec!["to", "back"],
+            ("look", [])       => vec!["at"],
+            ("talk", [])       => vec!["to"],
+            ("statusline", []) => vec!["on", "off", "toggle"],
+            ("help", [])       => commands::verbs(),
+            _                  => Vec::new(),
+        };
+        let rest = match before[1..].first() {
+            Some(&"at") | Some(&"to") => &before[2..],
+            _                         => &before[1..],
+        };
+        if rest.is_empty() {
+            words.extend(match verb {
+                "go"     => strs(&self.directions),
+                "look"   => strs(&self.nouns),
+                "talk"   => strs(&self.people),
+                "attack" => strs(&self.enemies),
+                _        => Vec::new(),
+            });
+        }
+        words
+    }
+
+    /// Finds the word being typed at the given spot in the line, and
+    /// every word it could be the start of. Only the last of several
+    /// commands on one line counts.
+    ///
+    /// Returns where the word starts, and what it could be.
+
+    fn matches(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let line = &line[..pos];
+        let command = match line.rfind(&['.', ','][..]) {
+            Some(i) => &line[i + 1..],
+            None    => line,
+        };
+        let partial = if command.ends_with(char::is_whitespace) {
+            ""
+        } else {
+            command.split_whitespace().last().unwrap_or("")
+        };
+        let start = pos - partial.len();
+        let before: Vec<String> = command[..command.len() - partial.len()]
+                                      .split_whitespace()
+                                      .map(|w| w.to_ascii_lowercase())
+                                      .collect();
+        let before: Vec<&str> = before.iter().map(|w| w.as_str()).collect();
+        let before = match before.iter().rposition(|w| *w == "then") {
+            Some(i) => &before[i + 1..],
+            None    => &before[..],
+        };
+        let partial = partial.to_ascii_lowercase();
+        let mut found: Vec<String> = self.choices(before)
+                                         .into_iter()
+                                         .filter(|w| w.starts_with(&partial))
+                                         .map(|w| w.to_string())
+                                         .collect();
+        found.sort();
+        found.dedup();
+        (start, found)
+    }
+}
+
+/// Borrows a list of words as plain strs.
+
+fn strs(words: &[String]) -> Vec<&str> {
+    words.iter().map(|w| w.as_str()).collect()
+}
+
+impl Completer for InputHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize,
+                _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.matches(line, pos))
+    }
+}
+
+impl Hinter for InputHelper {
+
+    /// Shows the rest of the word being typed, once
+    /// there's only one thing it could be.
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None
+        }
+        let (start, found) = self.matches(line, pos);
+        match found.as_slice() {
+            [word] if pos > start && word.len() > pos - start =>
+                Some(word[pos - start..].to_string()),
+            _                                                 => None,
+        }
+    }
+}
+
+impl Highlighter for InputHelper {
+
+    /// Hints are shown in grey, to set them apart from what's typed.
+
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(format!("\x1b[90m{}\x1b[0m", hint))
+    }
+}
+
+impl Helper for InputHelper {}
+
+#[test]
+fn matches_test() {
+    let mut helper = InputHelper::default();
+    let gstate = State::new(1,false,false,false,false,false,false,false,false);
+    let carl = Npc::new("Carl".to_string(), String::new(), String::new(),
+                        String::new(), String::new(), 1);
+    helper.refresh(&gstate, &[carl], &[], &["cell", "wall"],
+                   &[("t".to_string(), "talk".to_string())]);
+    assert_eq!((0, vec!["status".to_string(), "statusline".to_string()]),
+               helper.matches("stat", 4));
+    assert_eq!((3, vec!["south".to_string()]), helper.matches("go s", 4));
+    assert_eq!((8, vec!["wall".to_string()]), helper.matches("look at w", 9));
+    assert_eq!((2, vec!["carl".to_string(), "to".to_string()]),
+               helper.matches("t ", 2));
+    // Only the last command on the line counts.
+    assert_eq!((11, vec!["north".to_string()]),
+               helper.matches("look, then n", 12));
+}
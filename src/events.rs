@@ -0,0 +1,228 @@
+use crate::helpers;
+use crate::npcs::Npc;
+use crate::room;
+use crate::scripts;
+use crate::state::{State, FLAGS};
+
+/// Represents something that happens by itself once its trigger
+/// goes off, as declared in the events data file.
+///
+/// Triggers are 'enter <room>', 'leave <room>', 'flag <name>'
+/// (the flag getting set), and 'turn <number>'. Actions are
+/// 'print <text>', 'set <flag>', 'npc <name> <room>',
+/// 'item <item> <room>', 'text <room> <file>', 'script <file>',
+/// and 'end'.
+/// An event only ever fires once, unless marked 'repeat'.
+
+pub struct Event {
+    pub id: String,
+    pub trigger: String,
+    pub repeat: bool,
+    pub actions: Vec<String>,
+}
+
+/// Reads every event out of the blocks of an events file.
+/// Each block starts with the event's id and its trigger,
+/// followed by one action per line.
+
+pub fn parse(blocks: &[Vec<String>]) -> Vec<Event> {
+    let mut events = Vec::new();
+    for block in blocks {
+        let mut lines = block.iter();
+        let (id, trigger) = match (lines.next(), lines.next()) {
+            (Some(id), Some(trigger)) => (id, trigger),
+            _                         => continue,
+        };
+        let mut event = Event { id: id.to_string(),
+                                trigger: trigger.to_string(),
+                                repeat: false,
+                                actions: Vec::new() };
+        for line in lines {
+            if line == "repeat" {
+                event.repeat = true;
+            } else {
+                event.actions.push(line.to_string());
+            }
+        }
+        events.push(event);
+    }
+    events
+}
+
+/// Loads the events from the file at the given path.
+
+pub fn load(path: &str) -> Vec<Event> {
+    parse(&helpers::read_blocks(path))
+}
+
+/// Works out which triggers went off between two game states,
+/// such as before and after the player's last command.
+
+pub fn triggers(before: &State, after: &State) -> Vec<String> {
+    let mut fired = Vec::new();
+    if before.curr_room != after.curr_room {
+        fired.push(format!("leave {}", before.curr_room));
+        fired.push(format!("enter {}", after.curr_room));
+    }
+    for (i, name) in FLAGS.iter().enumerate() {
+        let number = i as u8 + 1;
+        if !before.flag(number) && after.flag(number) {
+            fired.push(format!("flag {}", name));
+        }
+    }
+    for turn in before.turns + 1..=after.turns {
+        fired.push(format!("turn {}", turn));
+    }
+    fired
+}
+
+/// Whether any event is due to fire between the two game states.
+
+pub fn fires(events: &[Event], before: &State, after: &State) -> bool {
+    triggers(before, after).iter()
+                           .any(|t| events.iter()
+                                          .any(|e| &e.trigger == t
+                                                   && !after.fired.contains(&e.id)))
+}
+
+/// Fires every event whose trigger went off between the two
+/// game states, carrying out its actions in order.
+///
+/// Returns the game state and NPCs after every event has fired.
+
+pub fn run(events: &[Event], before: &State, mut gstate: State,
+           mut npcs: Vec<Npc>) -> (State, Vec<Npc>) {
+    for trigger in triggers(before, &gstate) {
+        for event in events.iter().filter(|e| e.trigger == trigger) {
+            if gstate.fired.contains(&event.id) {
+                continue;
+            }
+            if !event.repeat {
+                gstate.fired.push(event.id.clone());
+            }
+            for action in event.actions.iter() {
+                let temp = act(action, gstate, npcs);
+                gstate = temp.0;
+                npcs = temp.1;
+            }
+        }
+    }
+    (gstate, npcs)
+}
+
+/// Carries out a single event action.
+/// Actions that can't be understood are skipped.
+
+fn act(action: &str, gstate: State, mut npcs: Vec<Npc>) -> (State, Vec<Npc>) {
+    let mut words = action.splitn(2, ' ');
+    let verb = words.next().unwrap_or("");
+    let rest = words.next().unwrap_or("").trim();
+    let mut args = rest.split_whitespace();
+    let gstate = match verb {
+        "print"  =>
+            {
+                println!("\n\n{}", rest.replace("\\n", "\n"));
+                gstate
+            },
+        "set"    =>
+            match FLAGS.iter().position(|&f| f == rest) {
+                Some(i) => gstate.update((i as u8 + 1, true)),
+                None    => gstate,
+            },
+        "npc"    =>
+            {
+                let name = args.next().unwrap_or("");
+                if let Some(room) = args.next().and_then(|r| r.parse().ok()) {
+                    for npc in npcs.iter_mut().filter(|n| n.name == name) {
+                        npc.location = room;
+                    }
+                }
+                gstate
+            },
+        "item"   =>
+            {
+                let item = args.next().unwrap_or("");
+                match args.next().and_then(|r| r.parse().ok()) {
+                    Some(room) => move_item(gstate, item, room),
+                    None       => gstate,
+                }
+            },
+        "text"   =>
+            {
+                let room = args.next().and_then(|r| r.parse().ok());
+                match (room, args.next()) {
+                    (Some(room), Some(path)) => {
+                        let mut room_texts: Vec<(usize, String)> =
+                            gstate.room_texts.iter()
+                                             .filter(|t| t.0 != room)
+                                             .cloned()
+                                             .collect();
+                        room_texts.push((room, path.to_string()));
+                        State { room_texts, ..gstate }
+                    },
+                    _                        => gstate,
+                }
+            },
+        "script" => scripts::run_file(rest, gstate).0,
+        "end"    => State { curr_room: room::OUTSIDE, ..gstate },
+        _        => gstate,
+    };
+    (gstate, npcs)
+}
+
+/// Puts a quest item in another room.
+
+fn move_item(gstate: State, item: &str, room: usize) -> State {
+    match item {
+        "key"   => State { key_room: room, ..gstate },
+        "broom" => State { broom_room: room, ..gstate },
+        "nail"  => State { nail_room: room, ..gstate },
+        _       => gstate,
+    }
+}
+
+/// The description file an event has put in place
+/// of a room's usual one, if there is one.
+
+pub fn room_text(gstate: &State, room: usize) -> Option<&str> {
+    gstate.room_texts.iter()
+                     .find(|t| t.0 == room)
+                     .map(|t| t.1.as_str())
+}
+
+#[test]
+fn parse_test() {
+    let events = parse(&helpers::blocks("# A comment\nbell\nturn 3\n\
+                                         print Ding.\n\n\
+                                         door\nenter 1\nrepeat\n\
+                                         set took_key\n"));
+    assert_eq!(2, events.len());
+    assert_eq!("turn 3", events[0].trigger);
+    assert!(!events[0].repeat);
+    assert_eq!(vec!["set took_key".to_string()], events[1].actions);
+    assert!(events[1].repeat);
+}
+
+#[test]
+fn run_test() {
+    let events = parse(&helpers::blocks("unlocked\nflag final_room_unlocked\n\
+                                         text 6 ../data/room/r6text1.txt\n\
+                                         npc Blimpo 7\n\n\
+                                         late\nturn 2\nitem nail 3\nend\n"));
+    let before = State::empty();
+    let npcs = vec![Npc::new("Blimpo".to_string(), String::new(),
+                             String::new(), String::new(),
+                             String::new(), 6)];
+    let after = before.update((7, true));
+    let (gstate, npcs) = run(&events, &before, after, npcs);
+    assert_eq!(Some("../data/room/r6text1.txt"), room_text(&gstate, 6));
+    assert_eq!(7, npcs[0].location);
+    assert_eq!(vec!["unlocked".to_string()], gstate.fired);
+    // Time passing sets off the timed event, but only once.
+    let later = gstate.clone().tick().tick();
+    let (ended, npcs) = run(&events, &gstate, later, npcs);
+    assert_eq!(room::OUTSIDE, ended.curr_room);
+    assert_eq!(3, ended.nail_room);
+    let (again, _) = run(&events, &gstate, ended.clone().tick(), npcs);
+    assert_eq!(2, again.fired.len());
+}
@@ -0,0 +1,111 @@
+use crate::helpers::print_from_file;
+use crate::room;
+use crate::state::State;
+
+const CAPTURED_PATH: &str = "../data/misc/captured.txt";
+
+/// Represents a guard, who walks the same patrol route,
+/// one room per turn, for as long as the game goes on.
+
+pub struct Guard {
+    pub name: String,
+    pub route: Vec<usize>,
+}
+
+/// Implementation of Guard struct.
+
+impl Guard {
+
+    /// Create a new Guard with the given patrol route.
+    /// The route loops back around to its first room.
+
+    pub fn new(name: String, route: Vec<usize>) -> Guard {
+        Guard { name, route }
+    }
+
+    /// Works out which room the guard is in on the given turn.
+
+    pub fn location(&self, turn: u32) -> usize {
+        self.route[turn as usize % self.route.len()]
+    }
+}
+
+/// Creates a Vec of the guards patrolling the jail.
+
+pub fn generate_guards() -> Vec<Guard> {
+    vec![
+        // Sweeps between the break room, the furniture
+        // room, and the T-junction outside it.
+        Guard::new(
+                "A jailor".to_string(),
+                vec![10, 9, 3, 9, 10, 11],
+        ),
+    ]
+}
+
+/// Finds the first guard sharing a room with
+/// the player on the current turn, if any.
+
+pub fn spotted_by<'a>(guards: &'a [Guard], gstate: &State) -> Option<&'a Guard> {
+    guards.iter().find(|g| g.location(gstate.turns) == gstate.curr_room)
+}
+
+/// Called whenever a turn passes. Warns the player about any
+/// guard one room away, and runs the capture sequence if a guard
+/// walks in on the player, unless the player is in disguise.
+///
+/// Returns the game state after the guards have made their rounds.
+
+pub fn patrol(guards: &[Guard], gstate: State) -> State {
+    if let Some(guard) = spotted_by(guards, &gstate) {
+        if gstate.wearing_clothes {
+            println!("\n\n{} walks right past you, barely \
+                      glancing at your uniform.", guard.name);
+            return gstate
+        }
+        print_from_file(CAPTURED_PATH);
+        return captured(gstate)
+    }
+    for guard in guards {
+        let at = guard.location(gstate.turns);
+        if let Some(dir) = gstate.map.direction_to(gstate.curr_room, at) {
+            println!("\n\nYou hear heavy footsteps to the {}...", dir);
+        }
+    }
+    gstate
+}
+
+/// The player gets dragged back to their cell.
+/// Whatever they've found along the way, they keep,
+/// but there's no retracing how they got there.
+
+pub fn captured(gstate: State) -> State {
+    State { curr_room: room::CELL, trail: Vec::new(), ..gstate }
+}
+
+#[test]
+fn patrol_routes_follow_exits_test() {
+    let map = room::Map::classic();
+    for guard in generate_guards() {
+        let route = &guard.route;
+        for (i, &from) in route.iter().enumerate() {
+            let to = route[(i + 1) % route.len()];
+            assert!(map.direction_to(from, to).is_some());
+        }
+    }
+}
+
+#[test]
+fn spotted_by_test() {
+    let guards = vec![Guard::new("Test".to_string(), vec![3, 9])];
+    let mut gstate = State::empty();
+    gstate.curr_room = 9;
+    // On even turns the guard is at 3, on odd turns at 9.
+    assert!(spotted_by(&guards, &gstate).is_none());
+    gstate.turns = 1;
+    gstate.trail = vec![3];
+    assert!(spotted_by(&guards, &gstate).is_some());
+    let caught = captured(gstate);
+    assert_eq!(room::CELL, caught.curr_room);
+    assert!(caught.trail.is_empty());
+}
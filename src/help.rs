@@ -0,0 +1,137 @@
+use crate::aliases;
+use crate::combat::Enemy;
+use crate::commands;
+use crate::helpers;
+use crate::items;
+use crate::npcs::Npc;
+use crate::room;
+use crate::state::State;
+use crate::wing;
+
+/// Reads every help page out of the blocks of a help pages file,
+/// as (command, page) pairs. Each block starts with the name of
+/// its command.
+
+pub fn parse(blocks: &[Vec<String>]) -> Vec<(String, String)> {
+    blocks.iter()
+          .filter_map(|block| {
+              let (name, page) = block.split_first()?;
+              Some((name.clone(), page.join("\n")))
+          })
+          .collect()
+}
+
+/// Loads the help pages from the file at the given path.
+
+pub fn load(path: &str) -> Vec<(String, String)> {
+    parse(&helpers::read_blocks(path))
+}
+
+/// Lists every command the game understands, with how to use it,
+/// what it does, and the other names it goes by, followed by the
+/// player's aliases. Made from the same table the game reads
+/// commands with, so it never lists anything the game won't take.
+
+pub fn list(aliases: &[(String, String)]) -> String {
+    let mut text = String::new();
+    for (_, name, others, usage, about) in commands::COMMANDS.iter() {
+        text.push_str(&format!(" {:<24}-> {}\n", usage, about));
+        if !others.is_empty() {
+            text.push_str(&format!(" {:<24}   Also: {}\n", "", others.join(", ")));
+        }
+        let shortcuts: Vec<&str> = aliases.iter()
+                                          .filter(|a| &a.1 == name)
+                                          .map(|a| a.0.as_str())
+                                          .collect();
+        if !shortcuts.is_empty() {
+            text.push_str(&format!(" {:<24}   Shortcut: {}\n", "",
+                                   shortcuts.join(", ")));
+        }
+    }
+    let others: Vec<String> = aliases.iter()
+                                     .filter(|a| commands::canonical(&a.1).is_none())
+                                     .map(|a| format!("{} = {}", a.0, a.1))
+                                     .collect();
+    if !others.is_empty() {
+        text.push_str(&format!("\n  Your aliases: {}\n", others.join(", ")));
+    }
+    text
+}
+
+/// A short nudge about what the player could do in their room,
+/// for when they seem to be stuck.
+
+pub fn context_hint(gstate: &State, npcs: &[Npc], enemies: &[Enemy]) -> &'static str {
+    let room = gstate.curr_room;
+    let items = [items::KEY, items::BROOM, items::NAIL];
+    if enemies.iter().any(|e| e.location == room && e.is_alive()) {
+        "Something here looks ready for a fight."
+    } else if npcs.iter().any(|n| n.is_in(room)) {
+        "There is someone here you could talk to."
+    } else if items.iter().any(|i| items::is_here(gstate, i))
+              || wing::key_here(gstate, "key").is_some() {
+        "Something here might be worth a closer look."
+    } else {
+        "Enter 'exits' to see the ways out of here."
+    }
+}
+
+/// Shows the list of commands, along with a hint about the room
+/// the player is in, or the help page for a single command.
+/// Aliases and directions get the page of what they stand for.
+
+pub fn help_cmd(pages: &[(String, String)], aliases: &[(String, String)],
+                topic: &str, gstate: &State, npcs: &[Npc],
+                enemies: &[Enemy], path: &str) {
+    if topic.is_empty() {
+        helpers::print_from_file(path);
+        println!("{}", list(aliases));
+        println!("--------------------------------------------------------");
+        println!("\n{}", context_hint(gstate, npcs, enemies));
+        return
+    }
+    let expanded = aliases::expand_all(aliases, topic);
+    let word = expanded.first()
+                       .and_then(|c| c.split_whitespace().next())
+                       .unwrap_or("");
+    let name = match room::direction(word) {
+        Some(_) => Some("go"),
+        None    => commands::canonical(word),
+    };
+    match name.and_then(|n| pages.iter().find(|p| p.0 == n)) {
+        Some((name, page)) => println!("\n\n  ___{}___\n\n{}", name, page),
+        None               => println!("\n\nThere's no help for '{}'. \
+                                        Enter 'help' for a list of commands.",
+                                       topic),
+    }
+}
+
+#[test]
+fn list_test() {
+    let aliases = vec![("t".to_string(), "talk".to_string()),
+                       ("peek".to_string(), "look wall".to_string())];
+    let text = list(&aliases);
+    // Every command and other name the game takes is listed.
+    for verb in commands::verbs() {
+        assert!(text.contains(verb));
+    }
+    assert!(text.contains("Shortcut: t\n"));
+    assert!(text.contains("Your aliases: peek = look wall"));
+}
+
+#[test]
+fn pages_test() {
+    let pages = parse(&helpers::blocks(include_str!("../data/misc/commands.txt")));
+    // Every command has a help page.
+    for (_, name, _, _, _) in commands::COMMANDS.iter() {
+        assert!(pages.iter().any(|p| p.0 == *name), "no help page for {}", name);
+    }
+}
+
+#[test]
+fn parse_test() {
+    let pages = parse(&helpers::blocks("# Pages.\n\ngo\nUsage: go\nMoves.\n\n\
+                                        look\nUsage: look\n"));
+    assert_eq!(vec![("go".to_string(), "Usage: go\nMoves.".to_string()),
+                    ("look".to_string(), "Usage: look".to_string())], pages);
+}
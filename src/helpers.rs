@@ -1,5 +1,5 @@
-use std::fs;
-use std::io;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
 
 /// Takes input str that may or may not exist,
 /// determines whether it does, and then returns
@@ -24,6 +24,47 @@ pub fn print_from_file(path: &str) {
     println!("{}", file_text);
 }
 
+/// Reads the whole of one of the game's data files.
+///
+/// # Panics
+///
+/// Will panic if the file is missing, as the game
+/// expects its data files to all be present.
+
+pub fn read_data(path: &str) -> String {
+    fs::read_to_string(path)
+        .unwrap_or_else(|_| panic!("data file {} is missing", path))
+}
+
+/// Splits the text of a data file into its blocks, which are
+/// separated by blank lines. Each block is its lines, trimmed,
+/// leaving out comment lines starting with '#'.
+
+pub fn blocks(text: &str) -> Vec<Vec<String>> {
+    text.split("\n\n")
+        .map(|block| block.lines()
+                          .map(|l| l.trim())
+                          .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                          .map(|l| l.to_string())
+                          .collect::<Vec<String>>())
+        .filter(|lines| !lines.is_empty())
+        .collect()
+}
+
+/// Reads one of the game's data files as blocks, the way blocks()
+/// splits them up. Panics the same way read_data() does.
+
+pub fn read_blocks(path: &str) -> Vec<Vec<String>> {
+    blocks(&read_data(path))
+}
+
+#[test]
+fn blocks_test() {
+    let text = "# Heading\n\nfirst\n  indented  \n# note\n\n\nsecond\n";
+    assert_eq!(vec![vec!["first".to_string(), "indented".to_string()],
+                    vec!["second".to_string()]], blocks(text));
+}
+
 /// Pauses the game loop execution after showing info
 /// to the player, with the intention of letting the 
 /// player examine the information and then press the
@@ -34,3 +75,28 @@ pub fn wait_for_player() {
     let mut unused = String::new();
     let _ = io::stdin().read_line(&mut unused);
 }
+
+/// Reads a single line of input from the player,
+/// after displaying the given prompt, and returns
+/// it trimmed and converted to lowercase.
+
+pub fn read_choice(prompt: &str) -> String {
+    print!("{}", prompt);
+    let _ = io::stdout().flush();
+    let mut choice = String::new();
+    let _ = io::stdin().read_line(&mut choice);
+    choice.trim().to_ascii_lowercase()
+}
+
+/// Appends text to the end of a file,
+/// creating the file first if it doesn't exist.
+///
+/// Can return file write error.
+
+pub fn append_to_file(path: &str, text: &str) {
+    let mut file = OpenOptions::new().create(true)
+                                     .append(true)
+                                     .open(path)
+                                     .expect("Error opening file");
+    file.write_all(text.as_bytes()).expect("Error writing file");
+}
@@ -0,0 +1,111 @@
+use crate::helpers;
+use crate::items;
+use crate::state::{self, State, FLAGS};
+
+/// Represents the hints for one objective, as declared in the hints
+/// data file. The objective is done once its flag is set, and its
+/// hints go from vague to explicit.
+
+pub struct Hint {
+    pub objective: String,
+    pub tiers: Vec<String>,
+}
+
+/// Reads every objective's hints out of the blocks of a hints file.
+/// Each block starts with the objective's flag, followed by one
+/// hint per line.
+
+pub fn parse(blocks: &[Vec<String>]) -> Vec<Hint> {
+    let mut hints = Vec::new();
+    for block in blocks {
+        let mut lines = block.iter();
+        let objective = match lines.next() {
+            Some(objective) => objective.to_string(),
+            None            => continue,
+        };
+        let tiers: Vec<String> = lines.map(|l| l.to_string()).collect();
+        if !tiers.is_empty() {
+            hints.push(Hint { objective, tiers });
+        }
+    }
+    hints
+}
+
+/// Loads the hints from the file at the given path.
+
+pub fn load(path: &str) -> Vec<Hint> {
+    parse(&helpers::read_blocks(path))
+}
+
+/// Finds the objective the player should be working on: the
+/// first one that isn't done yet, or in a wing, the wing's own.
+
+fn current<'a>(hints: &'a [Hint], gstate: &State) -> Option<&'a Hint> {
+    if gstate.mode == "wing" {
+        return hints.iter().find(|h| h.objective == "wing")
+    }
+    hints.iter().find(|h| {
+        match FLAGS.iter().position(|&f| f == h.objective) {
+            Some(i) => !gstate.flag(i as u8 + 1),
+            None    => h.objective == "escape",
+        }
+    })
+}
+
+/// Fills in where the quest items are, since they
+/// can be anywhere in a shuffled game.
+
+fn fill(text: &str, gstate: &State) -> String {
+    let mut text = text.to_string();
+    for item in [items::KEY, items::BROOM, items::NAIL].iter() {
+        let room = gstate.map.name(items::location(gstate, item));
+        text = text.replace(&format!("{{{}}}", item), room);
+    }
+    text
+}
+
+/// Gives the player a hint for their current objective. Asking
+/// again for the same objective gives a more explicit hint, until
+/// they run out. Each new hint costs points off the final score,
+/// but doesn't take a turn.
+
+pub fn hint_cmd(hints: &[Hint], mut gstate: State) -> State {
+    let hint = match current(hints, &gstate) {
+        Some(hint) => hint,
+        None       => {
+            println!("\n\nYou're on your own here, I'm afraid.");
+            return gstate
+        }
+    };
+    let taken = gstate.hints.iter().filter(|h| **h == hint.objective).count();
+    let tier = taken.min(hint.tiers.len() - 1);
+    println!("\n\nHint {} of {}: {}", tier + 1, hint.tiers.len(),
+             fill(&hint.tiers[tier], &gstate));
+    if taken < hint.tiers.len() {
+        gstate.hints.push(hint.objective.clone());
+        println!("That hint cost you {} points.", state::HINT_COST);
+    }
+    gstate
+}
+
+#[test]
+fn hint_cmd_test() {
+    let hints = parse(&helpers::blocks("# Hints.\nexamined_wall\n\
+                                        Vague.\nExplicit.\n\n\
+                                        took_key\nIn the {key}.\n\n\
+                                        escape\nLeave.\n"));
+    let gstate = hint_cmd(&hints, State::empty());
+    let gstate = hint_cmd(&hints, gstate);
+    assert_eq!(vec!["examined_wall".to_string(); 2], gstate.hints);
+    // Out of hints for the wall, so the last one is free to repeat.
+    let mut gstate = hint_cmd(&hints, gstate);
+    assert_eq!(2, gstate.hints.len());
+    assert_eq!(4, gstate.hint_penalty());
+    // Hints can't take the score below nothing.
+    assert_eq!(0, gstate.current_score());
+    gstate.examined_wall = true;
+    assert_eq!("took_key", current(&hints, &gstate).unwrap().objective);
+    assert_eq!("In the Secret Room.", fill(&hints[1].tiers[0], &gstate));
+    gstate.took_key = true;
+    assert_eq!("escape", current(&hints, &gstate).unwrap().objective);
+}
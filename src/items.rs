@@ -0,0 +1,167 @@
+use crate::room::{self, Map};
+use crate::state::State;
+
+// Names of the quest items, as the player refers to them.
+pub const KEY: &str   = "key";
+pub const BROOM: &str = "broom";
+pub const NAIL: &str  = "nail";
+
+// Blimpo works on the final door from here, and
+// the nail has to be brought to him to open it.
+const BLIMPO_ROOM: usize = 6;
+
+/// Every room a quest item might be placed in when shuffling.
+/// Placements that can't be solved get thrown out.
+
+pub const ELIGIBLE_ROOMS: [usize; 13] = [0, 1, 2, 3, 4, 5, 6,
+                                         7, 8, 9, 10, 11, 12];
+
+/// Where each quest item starts out in the classic game.
+
+pub fn home_room(item: &str) -> usize {
+    match item {
+        KEY   => room::SECRET_ROOM,
+        BROOM => room::BROOM_ROOM,
+        _     => room::SMELLY_CELL,
+    }
+}
+
+/// Where the given quest item was placed in this game.
+
+pub fn location(gstate: &State, item: &str) -> usize {
+    match item {
+        KEY   => gstate.key_room,
+        BROOM => gstate.broom_room,
+        _     => gstate.nail_room,
+    }
+}
+
+/// Whether the player has picked up the given quest item.
+
+pub fn is_taken(gstate: &State, item: &str) -> bool {
+    match item {
+        KEY   => gstate.took_key,
+        BROOM => gstate.took_broom,
+        _     => gstate.took_nail,
+    }
+}
+
+/// Whether the player is still carrying the given item,
+/// which is true of a quest item until it's handed over,
+/// and of any key found in a generated wing.
+
+pub fn is_carried(gstate: &State, item: &str) -> bool {
+    match item {
+        KEY   => gstate.took_key,
+        BROOM => gstate.took_broom && !gstate.helped_carl,
+        NAIL  => gstate.took_nail && !gstate.final_room_unlocked,
+        other => gstate.keys.iter().any(|k| k == other),
+    }
+}
+
+/// Whether the given quest item is lying in the player's room.
+
+pub fn is_here(gstate: &State, item: &str) -> bool {
+    !is_taken(gstate, item) && location(gstate, item) == gstate.curr_room
+}
+
+/// Whether the given quest item was moved away from its usual
+/// room, which means that room's description shouldn't mention it.
+
+pub fn is_moved(gstate: &State, item: &str) -> bool {
+    location(gstate, item) != home_room(item)
+}
+
+/// Prints a line about each quest item that was placed in the
+/// player's room by shuffling, since the room's own description
+/// doesn't know about it. Meant to follow the room description.
+
+pub fn describe_here(gstate: &State) {
+    for &item in [KEY, BROOM, NAIL].iter() {
+        if is_here(gstate, item) && is_moved(gstate, item) {
+            match item {
+                KEY   => println!("A small key glints on the floor."),
+                BROOM => println!("A pathetic-looking broom leans \
+                                   against the wall."),
+                _     => println!("A long, crooked nail lies \
+                                   on the floor."),
+            }
+        }
+    }
+}
+
+/// Checks whether a game with the quest items in the given rooms
+/// can be finished, by working out which rooms the player could
+/// reach as they pick up each item and unlock each door.
+
+pub fn is_solvable(key_room: usize, broom_room: usize,
+                   nail_room: usize) -> bool {
+    let mut has_key = false;
+    let mut has_nail = false;
+    let mut met_blimpo = false;
+    let mut unlocked = false;
+    let map = Map::classic();
+    loop {
+        let rooms = map.reachable(&|lock| match lock {
+            room::LOCK_KEY    => has_key,
+            room::LOCK_STENCH => met_blimpo,
+            room::LOCK_BLIMPO => unlocked,
+            _                 => true,
+        });
+        let before = (has_key, has_nail, met_blimpo, unlocked);
+        has_key = has_key || rooms.contains(&key_room);
+        has_nail = has_nail || rooms.contains(&nail_room);
+        met_blimpo = met_blimpo || rooms.contains(&BLIMPO_ROOM);
+        unlocked = unlocked || (has_nail && met_blimpo);
+        if before == (has_key, has_nail, met_blimpo, unlocked) {
+            return rooms.contains(&room::FINAL_ROOM)
+                   && rooms.contains(&broom_room)
+        }
+    }
+}
+
+/// Places the quest items in random rooms, drawing from the game's
+/// rng, and keeps trying until the placement can be solved.
+///
+/// Returns the new game state in shuffle mode.
+
+pub fn shuffle(mut gstate: State) -> State {
+    let count = ELIGIBLE_ROOMS.len() as u32;
+    loop {
+        let key_room = ELIGIBLE_ROOMS[gstate.rng.range(0, count) as usize];
+        let broom_room = ELIGIBLE_ROOMS[gstate.rng.range(0, count) as usize];
+        let nail_room = ELIGIBLE_ROOMS[gstate.rng.range(0, count) as usize];
+        if is_solvable(key_room, broom_room, nail_room) {
+            return State {
+                mode: "shuffle".to_string(),
+                key_room,
+                broom_room,
+                nail_room,
+                ..gstate
+            }
+        }
+    }
+}
+
+#[test]
+fn is_solvable_test() {
+    // The classic layout had better work.
+    assert!(is_solvable(room::SECRET_ROOM, room::BROOM_ROOM,
+                        room::SMELLY_CELL));
+    // The key can't be outside the cell it unlocks,
+    assert!(!is_solvable(3, room::BROOM_ROOM, room::SMELLY_CELL));
+    // and the nail can't be behind the door it opens.
+    assert!(!is_solvable(room::CELL, room::BROOM_ROOM, room::FINAL_ROOM));
+}
+
+#[test]
+fn shuffle_test() {
+    for seed in 0..50 {
+        let mut gstate = State::empty();
+        gstate.rng = crate::rng::Rng::new(seed);
+        let shuffled = shuffle(gstate);
+        assert_eq!("shuffle", shuffled.mode);
+        assert!(is_solvable(shuffled.key_room, shuffled.broom_room,
+                            shuffled.nail_room));
+    }
+}
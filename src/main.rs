@@ -2,16 +2,42 @@
 //!
 //! A text adventure game in the spirit of classic games like Zork, etc.
 
+// The codebase deliberately leaves a blank line between
+// doc comments and the items they document.
+#![allow(clippy::empty_line_after_doc_comments)]
+
 extern crate rustyline;
 
 mod room;
 mod state;
 mod npcs;
 mod helpers;
+mod profile;
+mod guards;
+mod rng;
+mod combat;
+mod status;
+mod items;
+mod wing;
+mod events;
+mod scripts;
+mod scenery;
+mod automap;
+mod pronouns;
+mod commands;
+mod aliases;
+mod completion;
+mod help;
+mod hints;
 
+use commands::Command;
 use rustyline::Editor;
 use std::process;
 use std::fs;
+use std::env;
+use std::panic;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 // Filename constants; TODO: File path expansion
 const INTRO_PATH: &str        = "../data/misc/intro.txt";
@@ -19,12 +45,23 @@ const HISTORY_PATH: &str      = "../data/misc/history.txt";
 const SAVE_PATH: &str         = "../data/misc/savedgame.txt";
 const HELP_PATH: &str         = "../data/misc/help.txt";
 const MENU_PATH: &str         = "../data/misc/mainmenu.txt";
-const NPCS_DEF: &str          = "../data/npc/npcsdefault.txt";
-const STATE_DEF: &str         = "../data/state/statedefault.txt";
+const END_MENU_PATH: &str     = "../data/misc/endmenu.txt";
+const PROFILE_PATH: &str      = "../data/misc/profile.txt";
+const RUNS_PATH: &str         = "../data/misc/completedruns.txt";
+const EVENTS_PATH: &str       = "../data/misc/events.txt";
+const SCRIPTS_PATH: &str      = "../data/misc/scripts.txt";
+const SCENERY_PATH: &str      = "../data/misc/scenery.txt";
+const ALIASES_PATH: &str      = "../data/misc/aliases.txt";
+const PAGES_PATH: &str        = "../data/misc/commands.txt";
+const HINTS_PATH: &str        = "../data/misc/hints.txt";
 const BAD_ENDING_PATH: &str   = "../data/endings/badending.txt";
 const GOOD_ENDING0_PATH: &str = "../data/endings/goodending0.txt";
 const GOOD_ENDING1_PATH: &str = "../data/endings/goodending1.txt";
+const WING_ENDING_PATH: &str  = "../data/endings/wingending.txt";
 
+/// How many failed commands in a row it takes before
+/// the player gets a hint about what they could do.
+const STUCK_AFTER: u32 = 3;
 
 /// Contains the main game loop, the main menu loop,
 /// makes the rustyline Editor, loads rustyline history,
@@ -33,10 +70,19 @@ const GOOD_ENDING1_PATH: &str = "../data/endings/goodending1.txt";
 /// Many things will ultimately be abstracted out to helpers.
 
 pub fn main() {
+    // Command line options: --seed <number> and --transcript <file>.
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (seed_arg, transcript) = parse_args(&args);
     // rustyline structure, allows us to read input w/prompt.
-    let mut rl = Editor::<()>::new();
+    let mut rl = Editor::<completion::InputHelper>::new();
+    // Tab completes commands, directions, things and people.
+    rl.set_helper(Some(completion::InputHelper::default()));
     // Keep track of whether saved game or new game.
     let mut is_saved_game = false;
+    // Keep track of whether the quest items should be shuffled.
+    let mut is_shuffled = false;
+    // Keep track of whether to play in a generated wing instead.
+    let mut is_wing = false;
     // Print game intro text.
     helpers::print_from_file(INTRO_PATH);
     // Main menu loop
@@ -46,10 +92,34 @@ pub fn main() {
         match choice.to_ascii_lowercase().as_str() {
             "new" | "n"      => { break },
             "continue" | "c" => { is_saved_game = true; break },
+            "shuffle" | "s"  => { is_shuffled = true; break },
+            "wing" | "w"     => { is_wing = true; break },
+            "endings" | "achievements" | "e" | "a" =>
+                {
+                    profile::show_gallery(&profile::load(PROFILE_PATH));
+                    helpers::wait_for_player();
+                },
             "quit" | "q"     => { println!("\n\nExiting!"); process::exit(1) },
             _                => println!("\nInvalid choice!")
         }
     }
+    // Make sure any crash report says how to replay the game. The
+    // seed isn't known until the game is set up, and setting it up
+    // can crash too, if the saved game is broken.
+    let crash_seed = Arc::new(Mutex::new(None));
+    let hook_seed = Arc::clone(&crash_seed);
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        match hook_seed.lock().ok().and_then(|seed| *seed) {
+            Some(seed) => eprintln!("\nThe game crashed! Game seed: {}\n\
+                                     Please include the seed when \
+                                     reporting this.", seed),
+            None       => eprintln!("\nThe game crashed while loading!\n\
+                                     Please include {} when \
+                                     reporting this.", SAVE_PATH),
+        }
+    }));
     // Load previous input history if "continuing".
     if is_saved_game {
         println!("\n\nWelcome back!");
@@ -58,64 +128,576 @@ pub fn main() {
           .expect("history file is missing or inaccessible");
     }
     // Set a new game state or load the saved one.
-    // Do the same with the npcs and their states.
-    let (mut gstate, mut npcs) = {
+    // Do the same with the npcs, the enemies, and their states.
+    let (mut gstate, mut npcs, mut enemies) = {
         if is_saved_game { load_game() }
-        else { (state::State::empty(), npcs::generate_npcs()) }
+        else { (state::State::empty(), npcs::generate_npcs(),
+                combat::generate_enemies()) }
     };
+    // Everything random in the game draws from the state's rng.
+    // A saved game carries on with the seed it started with.
+    if is_saved_game {
+        if seed_arg.is_some() {
+            println!("\nIgnoring --seed; saved games keep their own seed.");
+        }
+        *crash_seed.lock().unwrap() = Some(gstate.rng.seed);
+    } else {
+        let seed = seed_arg.unwrap_or_else(|| {
+            SystemTime::now().duration_since(UNIX_EPOCH)
+                             .map(|d| d.as_secs())
+                             .unwrap_or(0)
+        });
+        gstate.rng = rng::Rng::new(seed);
+        *crash_seed.lock().unwrap() = Some(seed);
+        if is_shuffled {
+            gstate = items::shuffle(gstate);
+        } else if is_wing {
+            gstate = wing::new_game(gstate);
+        }
+    }
+    if let Some(path) = &transcript {
+        helpers::append_to_file(path, &format!("seed: {}\n", gstate.rng.seed));
+    }
+    // Guards patrol on a fixed schedule, so they never need saving.
+    let guards = guards::generate_guards();
+    // Events are declared in data, and never change mid-game.
+    let events = events::load(EVENTS_PATH);
+    // Scripts attached to verbs get the first say on commands.
+    let hooks = scripts::load(SCRIPTS_PATH);
+    // Everything the player can look at, room by room.
+    let scenery = scenery::load(SCENERY_PATH);
+    // Shortcuts for commands, both built in and the player's own.
+    let mut aliases = aliases::load(ALIASES_PATH);
+    // What 'help' has to say about each command.
+    let pages = help::load(PAGES_PATH);
+    // Hints for each objective, from vague to explicit.
+    let hints = hints::load(HINTS_PATH);
+    // The room last described to the player; it's only
+    // described again once the player ends up somewhere else.
+    let mut shown_room = None;
+    // Steps left to take when traveling somewhere, as
+    // (direction, room it should lead to) pairs.
+    let mut travel: Vec<(String, usize)> = Vec::new();
+    // Who and what 'him', 'it' and so on refer to.
+    let mut refs = pronouns::Referents::default();
+    // Commands still to come from the last line the player typed.
+    let mut queued: Vec<String> = Vec::new();
+    // The last command carried out, for 'again', and the last one
+    // that had a word the game didn't understand, for 'oops'.
+    let mut last_input = String::new();
+    let mut mistake: Option<(String, String)> = None;
+    // How many commands in a row have failed.
+    let mut stuck = 0;
     // Main game / user command loop.
     loop {
         // Display the current room description to the player.
-        room::get_desc(gstate.curr_room, gstate.took_key,
-                       gstate.examined_wall, gstate.took_broom,
-                       gstate.took_nail, gstate.final_room_unlocked);
-        // Prompt for user input: command [argument]
-        let input = rl.readline("\n> ").expect("Readline error");
-        // Add this input to user input history.
-        rl.add_history_entry(input.as_ref());
+        if shown_room != Some(gstate.curr_room) {
+            gstate = arrive(gstate);
+            shown_room = Some(gstate.curr_room);
+        }
+        // Traveling takes the next step by itself.
+        let stepping = !travel.is_empty();
+        let input = if stepping {
+            format!("go {}", travel[0].0)
+        } else if !queued.is_empty() {
+            queued.remove(0)
+        } else {
+            // What can be completed depends on where the player is.
+            if let Some(helper) = rl.helper_mut() {
+                helper.refresh(&gstate, &npcs, &enemies,
+                               &nouns_here(&gstate, &scenery), &aliases);
+            }
+            // Prompt for user input: command [argument]
+            let input = rl.readline(&prompt(&gstate)).expect("Readline error");
+            // Add this input to user input history.
+            rl.add_history_entry(input.as_str());
+            if let Some(path) = &transcript {
+                helpers::append_to_file(path, &format!("> {}\n", input));
+            }
+            // One line can hold several commands, run one at a time,
+            // and an alias can stand for several commands itself.
+            // Only what the player typed is expanded, so commands
+            // that come out of it are never expanded a second time.
+            queued = commands::split(&input)
+                         .iter()
+                         .flat_map(|c| aliases::expand_all(&aliases, c))
+                         .collect();
+            if queued.is_empty() {
+                String::new()
+            } else {
+                queued.remove(0)
+            }
+        };
+        // 'again' and 'oops' work off of earlier commands.
+        let input = match commands::recall(&input, &last_input, &mistake) {
+            Ok(input)    => input,
+            Err(message) => {
+                println!("\n\n{}", message);
+                queued.clear();
+                continue;
+            }
+        };
+        // Pronouns stand for whatever the player last referred to,
+        // and the player gets asked if that isn't clear. An alias
+        // keeps its pronouns, to stand for whatever they mean later.
+        let defining = input.split_whitespace()
+                            .next()
+                            .map(|w| w.to_ascii_lowercase())
+                            .as_deref()
+                            .and_then(commands::canonical) == Some("alias");
+        let input = match pronouns::substitute(&refs, &input) {
+            _ if defining => input,
+            Ok(input)     => input,
+            Err(question) => {
+                println!("\n\n{}", question);
+                queued.clear();
+                continue;
+            }
+        };
         // Split up the words in the user's input; only using first two.
         let mut input_iter = input.as_str().split_whitespace();
-        // Parse the user's command and the argument following it.
+        // Parse the user's command and the argument following it,
+        // skipping the 'at' in commands like 'look at wall'.
         let cmd = helpers::parse_input(input_iter.next());
-        let arg = helpers::parse_input(input_iter.next());
-        // Use input to execute the desired command as best we can.
-        match cmd.as_str() {
-            "go" | "move" | "walk" =>
+        // Other names for a command all mean the command itself.
+        let cmd = commands::canonical(&cmd).map_or(cmd, |c| c.to_string());
+        let mut arg = helpers::parse_input(input_iter.next());
+        if arg == "at" {
+            arg = helpers::parse_input(input_iter.next());
+        }
+        // Some commands take everything after the command word.
+        let rest = input.split_whitespace()
+                        .skip(1)
+                        .collect::<Vec<&str>>()
+                        .join(" ")
+                        .to_ascii_lowercase();
+        // Guards only move when time passes, and events
+        // fire off of whatever changed during the turn.
+        let turn_before = gstate.turns;
+        let state_before = gstate.clone();
+        // A script attached to the command may handle it instead.
+        let handled = match scripts::find(&hooks, &cmd, &arg, gstate.curr_room) {
+            Some(path) =>
                 {
-                    gstate = go_cmd(gstate, arg.as_str());
-                    helpers::wait_for_player();
+                    let temp = scripts::run_file(path, gstate);
+                    gstate = temp.0;
+                    temp.1
                 },
-            "look" | "examine"     => 
-                {
-                    gstate = look_cmd(gstate, arg.as_str());
-                    helpers::wait_for_player();
-                },
-            "talk" | "speak" | "t" => 
+            None       => false,
+        };
+        let mut invalid = false;
+        // Use input to execute the desired command as best we can.
+        match commands::lookup(&cmd) {
+            _ if handled                  => {},
+            Some(Command::Go) if arg == "to" =>
+                travel = travel_cmd(&gstate, rest[2..].trim()),
+            Some(Command::Travel)         =>
+                travel = travel_cmd(&gstate, rest.as_str()),
+            Some(Command::Go) if arg == "back" =>
+                gstate = back_cmd(gstate),
+            Some(Command::Back)           =>
+                gstate = back_cmd(gstate),
+            Some(Command::Go)             =>
+                gstate = go_cmd(gstate, arg.as_str()),
+            Some(Command::Look) if arg.is_empty() =>
+                describe_room(&gstate),
+            Some(Command::Look)           =>
+                gstate = look_cmd(gstate, arg.as_str(), &scenery),
+            Some(Command::Talk)           =>
                 {
                     let temp = talk_cmd(npcs, gstate);
                     npcs = temp.0;
                     gstate = temp.1;
-                    helpers::wait_for_player();
                 },
-            "help" | "?"           =>
+            Some(Command::Attack)         =>
                 {
-                    helpers::print_from_file(HELP_PATH);
-                    helpers::wait_for_player();
+                    let temp = combat::attack_cmd(gstate, enemies,
+                                                  arg.as_str());
+                    gstate = temp.0;
+                    enemies = temp.1;
                 },
-            "quit" | "exit" | "q"  =>
+            Some(Command::Wait)           =>
+                {
+                    println!("\n\nTime passes...");
+                    gstate = gstate.tick();
+                },
+            Some(Command::Exits)          =>
+                exits_cmd(&gstate),
+            Some(Command::Map)            =>
+                automap::map_cmd(&gstate, &npcs),
+            Some(Command::StatusLine)     =>
+                gstate = status_line_cmd(gstate, arg.as_str()),
+            Some(Command::Verbose) | Some(Command::Brief) | Some(Command::SuperBrief) =>
+                gstate = verbosity_cmd(gstate, cmd.as_str()),
+            Some(Command::Score)          =>
+                score_cmd(&gstate),
+            Some(Command::Status)         =>
+                status::status_cmd(&gstate),
+            Some(Command::Alias)          =>
+                {
+                    let updated = aliases::alias_cmd(&aliases, rest.as_str());
+                    if updated != aliases {
+                        aliases::save(ALIASES_PATH, &updated);
+                        aliases = updated;
+                    }
+                },
+            Some(Command::Help)           =>
+                help::help_cmd(&pages, &aliases, rest.as_str(), &gstate,
+                               &npcs, &enemies, HELP_PATH),
+            Some(Command::Hint)           =>
+                gstate = hints::hint_cmd(&hints, gstate),
+            // These were already worked out into other commands.
+            Some(Command::Again) | Some(Command::Oops) => {},
+            Some(Command::Quit)           =>
                 {
                     println!("\n\nExiting!"); 
                     rl.save_history(HISTORY_PATH).unwrap();
-                    save_game(gstate.serialize(), npcs::serialize(npcs));
+                    save_game(gstate.serialize(), npcs::serialize(&npcs),
+                              combat::serialize(&enemies));
                     process::exit(1)
                 },
-            other                  =>
+            None if room::direction(&cmd).is_some() =>
+                gstate = go_cmd(gstate, &cmd),
+            None                          =>
                 {
-                    println!("\n{} is not a valid command.", other);
-                    helpers::wait_for_player();
+                    println!("\n{} is not a valid command.", cmd);
+                    invalid = true;
                 },
         }
+        // A command that didn't work stops the rest of the line,
+        // and leaves the word it tripped over to be fixed with 'oops'.
+        let failed = invalid || match cmd.as_str() {
+            _ if handled        => false,
+            "go" if arg == "to" => travel.is_empty(),
+            "travel"            => travel.is_empty(),
+            other               => commands::takes_turn(other, &arg)
+                                   && gstate.turns == turn_before,
+        };
+        let mut suggestion = None;
+        if !stepping {
+            let bad = if invalid { cmd.clone() } else { arg.clone() };
+            mistake = if failed && !bad.is_empty() {
+                Some((input.clone(), bad))
+            } else {
+                None
+            };
+            if let Some((line, bad)) = &mistake {
+                let words = words_for(&gstate, &enemies, &scenery,
+                                      &cmd, invalid);
+                suggestion = commands::suggest(line, bad, &words);
+            }
+            last_input = input;
+            // Someone whose commands keep going nowhere gets a nudge.
+            stuck = if failed { stuck + 1 } else { 0 };
+            if stuck == STUCK_AFTER {
+                println!("\n\n{}", help::context_hint(&gstate, &npcs, &enemies));
+                stuck = 0;
+            }
+        }
+        if failed {
+            queued.clear();
+        }
+        refs = pronouns::note(refs, &cmd, &arg, !failed,
+                              state_before.curr_room, &npcs, &enemies);
+        if gstate.turns != turn_before {
+            gstate = guards::patrol(&guards, gstate);
+            gstate = combat::enemy_turn(&enemies, gstate);
+            gstate = status::wear_off(gstate);
+        }
+        let interrupted = events::fires(&events, &state_before, &gstate)
+                          || gstate.health < state_before.health;
+        let temp = events::run(&events, &state_before, gstate, npcs);
+        gstate = temp.0;
+        npcs = temp.1;
+        if stepping {
+            let (_, expected) = travel.remove(0);
+            // Anything unexpected along the way cuts the trip short.
+            if gstate.curr_room != expected
+               || (interrupted && !travel.is_empty()) {
+                println!("\n\nYou stop where you are.");
+                travel.clear();
+                queued.clear();
+            }
+        }
+        // Nobody needs to stop and read every step of a trip,
+        // or every command in a line of them.
+        // A likely fix for a mistyped command can be taken up
+        // with a single key.
+        if let Some(fixed) = suggestion {
+            println!("\n\nDid you mean '{}'?", fixed);
+            let choice = helpers::read_choice("\n\n > Press Y for that, \
+                                               or RETURN to continue <\n");
+            if choice == "y" {
+                queued.insert(0, fixed);
+            }
+        } else if travel.is_empty() && queued.is_empty() {
+            helpers::wait_for_player();
+        }
+        // Leaving through the final door ends the run.
+        if gstate.curr_room == room::OUTSIDE {
+            let temp = game_end(gstate, npcs, enemies);
+            gstate = temp.0;
+            npcs = temp.1;
+            enemies = temp.2;
+            // Whether rewound or starting over, the game picks up
+            // fresh: the rest of the line the run ended on, and
+            // anything said during it, belong to the run that's over.
+            travel.clear();
+            queued.clear();
+            last_input.clear();
+            mistake = None;
+            refs = pronouns::Referents::default();
+            stuck = 0;
+        }
+    }
+}
+
+/// The words that would have made sense in place of the one
+/// the player got wrong: a command, a direction, something to
+/// look at in the room, or an enemy to fight.
+
+fn words_for<'a>(gstate: &state::State, enemies: &'a [combat::Enemy],
+                 scenery: &'a [scenery::Scenery], cmd: &str,
+                 bad_command: bool) -> Vec<&'a str> {
+    let directions = room::DIRECTIONS.iter().map(|d| d.0);
+    if bad_command {
+        return commands::verbs().into_iter().chain(directions).collect()
+    }
+    match cmd {
+        "go"     => directions.collect(),
+        "look"   => nouns_here(gstate, scenery),
+        "attack" =>
+            enemies.iter()
+                   .filter(|e| e.location == gstate.curr_room)
+                   .map(|e| e.name.as_str())
+                   .collect(),
+        _        => Vec::new(),
+    }
+}
+
+/// Everything in the player's room, or on them, that they
+/// could look at: scenery, quest items, and wing keys.
+
+fn nouns_here<'a>(gstate: &state::State,
+                  scenery: &'a [scenery::Scenery]) -> Vec<&'a str> {
+    let mut words = scenery::names_in_scope(scenery, gstate);
+    let items = [items::KEY, items::BROOM, items::NAIL];
+    words.extend(items.iter().filter(|i| items::is_here(gstate, i)));
+    if wing::key_here(gstate, "key").is_some() {
+        words.push("key");
+    }
+    words
+}
+
+/// Reads the command line options the game understands:
+/// '--seed <number>' to fix the random seed of a new game, and
+/// '--transcript <file>' to record the seed and every command entered.
+/// Anything else is ignored.
+///
+/// Returns the seed and the transcript path, if given.
+
+pub fn parse_args(args: &[String]) -> (Option<u64>, Option<String>) {
+    let mut seed = None;
+    let mut transcript = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--seed"       =>
+                seed = iter.next().and_then(|s| s.parse().ok()),
+            "--transcript" =>
+                transcript = iter.next().cloned(),
+            _              => {},
+        }
+    }
+    (seed, transcript)
+}
+
+#[test]
+fn parse_args_test() {
+    let args: Vec<String> = ["--transcript", "run.txt", "--seed", "42"]
+        .iter().map(|a| a.to_string()).collect();
+    assert_eq!((Some(42), Some("run.txt".to_string())), parse_args(&args));
+    assert_eq!((None, None), parse_args(&[]));
+}
+
+/// Displays the description of the player's current room,
+/// followed by anything out of the ordinary about it,
+/// and about the player.
+
+pub fn describe_room(gstate: &state::State) {
+    match gstate.map.desc(gstate.curr_room) {
+        // Generated rooms carry their own descriptions.
+        Some(desc) => println!("\n\n{}", desc),
+        None       => helpers::print_from_file(desc_path(gstate)),
+    }
+    describe_extras(gstate);
+}
+
+/// The data file describing the player's current room,
+/// for rooms that don't carry their own description.
+
+fn desc_path(gstate: &state::State) -> &str {
+    // An event may have changed how the room looks for good.
+    if let Some(path) = events::room_text(gstate, gstate.curr_room) {
+        return path
+    }
+    // A room whose quest item was shuffled away, or was already
+    // picked up, gets the description without the item in it.
+    room::desc_path(gstate.curr_room,
+                    gstate.took_key || items::is_moved(gstate, items::KEY),
+                    gstate.examined_wall,
+                    gstate.took_broom || items::is_moved(gstate, items::BROOM),
+                    gstate.took_nail || items::is_moved(gstate, items::NAIL))
+}
+
+/// Displays just the name of the player's current room, and
+/// optionally the ways out of it that the player knows are open,
+/// followed by the same extras as the full description.
+
+pub fn describe_brief(gstate: &state::State, with_exits: bool) {
+    println!("\n\n{}", gstate.map.name(gstate.curr_room));
+    if with_exits {
+        let open: Vec<&str> = gstate.map.exits_from(gstate.curr_room)
+                                        .into_iter()
+                                        .filter(|e| e.lock.is_empty()
+                                                    || gstate.has_opened(&e.lock))
+                                        .map(|e| e.dir.as_str())
+                                        .collect();
+        if open.is_empty() {
+            println!("Exits: none.");
+        } else {
+            println!("Exits: {}.", open.join(", "));
+        }
+    }
+    describe_extras(gstate);
+}
+
+/// Displays anything out of the ordinary about the
+/// player's room, and about the player.
+
+fn describe_extras(gstate: &state::State) {
+    items::describe_here(gstate);
+    wing::describe_keys(gstate);
+    status::describe(gstate);
+}
+
+/// Describes the room the player just arrived in, as fully as their
+/// verbosity setting calls for: 'verbose' always gives the full
+/// description, 'brief' only does on the first visit, and
+/// 'superbrief' only ever gives the room's name.
+///
+/// Returns a new game state with the room marked as visited.
+
+pub fn arrive(gstate: state::State) -> state::State {
+    let first_visit = !gstate.visited.contains(&gstate.curr_room);
+    match gstate.verbosity.as_str() {
+        "verbose"    => describe_room(&gstate),
+        "superbrief" => describe_brief(&gstate, false),
+        _            =>
+            {
+                if first_visit {
+                    describe_room(&gstate);
+                } else {
+                    describe_brief(&gstate, true);
+                }
+            }
+    }
+    if !first_visit {
+        return gstate
+    }
+    let mut visited = gstate.visited.clone();
+    visited.push(gstate.curr_room);
+    state::State { visited, ..gstate }
+}
+
+#[test]
+fn arrive_test() {
+    let mut gstate = state::State::new(1,false,false,false,false
+                                      ,false,false,false,false);
+    gstate.verbosity = "superbrief".to_string();
+    let seen = arrive(gstate);
+    assert_eq!(vec![1], seen.visited);
+    // Coming back doesn't count as a new visit.
+    assert_eq!(vec![1], arrive(seen).visited);
+}
+
+/// The prompt shown when asking for a command, which starts with
+/// a status line if the player has turned it on.
+
+pub fn prompt(gstate: &state::State) -> String {
+    if gstate.status_line {
+        format!("\n[{} | Turn {} | Score {}]\n> ",
+                gstate.map.name(gstate.curr_room), gstate.turns, gstate.current_score())
+    } else {
+        "\n> ".to_string()
+    }
+}
+
+#[test]
+fn prompt_test() {
+    let mut gstate = state::State::empty();
+    assert_eq!("\n> ", prompt(&gstate));
+    gstate.status_line = true;
+    assert_eq!("\n[Your Cell | Turn 0 | Score 0]\n> ", prompt(&gstate));
+}
+
+/// Turns the status line in the prompt 'on' or 'off',
+/// or flips it when not told which. Doesn't take a turn.
+
+pub fn status_line_cmd(gstate: state::State, arg: &str) -> state::State {
+    let status_line = match arg {
+        "on"  => true,
+        "off" => false,
+        _     => !gstate.status_line,
+    };
+    if status_line {
+        println!("\n\nThe status line is on.");
+    } else {
+        println!("\n\nThe status line is off.");
+    }
+    state::State { status_line, ..gstate }
+}
+
+/// Lists the ways out of the player's room, and whether each is
+/// blocked. Rooms already visited are named. Secret passages stay
+/// off the list until they're found. Doesn't take a turn.
+
+pub fn exits_cmd(gstate: &state::State) {
+    println!("\n\nExits from {}:", gstate.map.name(gstate.curr_room));
+    let mut listed = false;
+    for exit in gstate.map.exits_from(gstate.curr_room) {
+        let open = exit.lock.is_empty() || gstate.has_opened(&exit.lock);
+        if !open && room::is_secret(&exit.lock) {
+            continue;
+        }
+        listed = true;
+        if !open {
+            println!("  {} (blocked)", exit.dir);
+        } else if gstate.visited.contains(&exit.to) {
+            println!("  {} to {}", exit.dir, gstate.map.name(exit.to));
+        } else {
+            println!("  {}", exit.dir);
+        }
+    }
+    if !listed {
+        println!("  None that you can see.");
+    }
+}
+
+/// Switches how much the player is told on arriving in a room.
+/// Doesn't take a turn.
+
+pub fn verbosity_cmd(gstate: state::State, mode: &str) -> state::State {
+    match mode {
+        "verbose"    => println!("\n\nMaximum verbosity. Rooms will be \
+                                  described in full every time."),
+        "superbrief" => println!("\n\nSuperbrief descriptions. Only room \
+                                  names will be shown.\n\
+                                  Use 'look' to see more."),
+        _            => println!("\n\nBrief descriptions. Rooms will only be \
+                                  described in full on your first visit."),
     }
+    state::State { verbosity: mode.to_string(), ..gstate }
 }
 
 /// Allows the player to move from one room to a room directly
@@ -124,217 +706,415 @@ pub fn main() {
 ///
 /// Returns a new game state with the updated current_room.
 /// If player can't go in the desired direction, returns old state.
+/// Moving to another room advances the turn counter.
+/// An exhausted player can't move at all.
 
 pub fn go_cmd(gstate: state::State, dir: &str) -> state::State {
-    state::State::new(
-        match dir {
-            "north" | "up" | "forward" =>
-                { 
-                    if gstate.curr_room == room::FINAL_ROOM {
-                        // Game done; Went north from final room.
-                        game_end(gstate.helped_carl, gstate.wearing_clothes);
-                        gstate.curr_room // Just satisfies the return type
-                    } else {
-                        room::go_north(gstate.curr_room,
-                                       gstate.took_key,
-                                       gstate.final_room_unlocked)
-                    }
-                }
-            "west" | "left"            =>
-                room::go_west(gstate.curr_room),
-            "south" | "down" | "back"  =>
-                room::go_south(gstate.curr_room, gstate.examined_wall),
-            "east" | "right"           =>
-                room::go_east(gstate.curr_room, gstate.met_blimpo),
-            other                      =>
-            {
-                println!("\n{} is not a valid direction.", other);
-                gstate.curr_room
-            }
-        },
-        gstate.examined_wall,
-        gstate.took_key,
-        gstate.took_broom,
-        gstate.helped_carl,
-        gstate.took_nail,
-        gstate.met_blimpo,
-        gstate.final_room_unlocked,
-        gstate.wearing_clothes,
-    )
+    if status::has(&gstate, status::EXHAUSTED) {
+        println!("\n\nYou're too exhausted to move. \
+                  Maybe wait a moment and catch your breath.");
+        return gstate
+    }
+    let dir = match room::direction(dir) {
+        Some(dir) => dir,
+        None      =>
+        {
+            println!("\n{} is not a valid direction.", dir);
+            return gstate
+        }
+    };
+    // The map knows where each way out leads, and what's locked.
+    let room_before = gstate.curr_room;
+    let next_room = room::go(&gstate.map, gstate.curr_room, dir,
+                             &|lock| gstate.has_opened(lock));
+    let moved = next_room != gstate.curr_room;
+    let mut gstate = state::State { curr_room: next_room, ..gstate };
+    if moved {
+        // Remember where the player came from, so they can go back.
+        gstate = gstate.step_from(room_before);
+    }
+    // Only actually going somewhere takes a turn.
+    if !moved {
+        gstate
+    } else if next_room == room::SMELLY_CELL {
+        // Nobody walks into that smell and comes out unaffected.
+        status::add(gstate.tick(), status::NAUSEATED)
+    } else {
+        gstate.tick()
+    }
 }
 
 #[test]
 fn go_cmd_test() {
-    let start0 = state::State::new(0,false,false,false,false
+    let start0 = state::State::new(2,false,false,false,false
+                                  ,false,false,false,false);
+    let start1 = state::State::new(2,false,false,false,false
                                   ,false,false,false,false);
-    let start1 = state::State::new(0,false,false,false,false
+    // Going north from room 2 leads to room 3, so this should be true
+    assert_eq!(3, go_cmd(start0, "north").curr_room);
+    // Going north from room 3 is not possible, so this should be true
+    assert_eq!(3, go_cmd(go_cmd(start1, "n"), "n").curr_room);
+}
+
+#[test]
+fn go_cmd_test_turns() {
+    let start = state::State::new(2,false,false,false,false
                                   ,false,false,false,false);
-    // Going up from room 2 leads to room 3, so this should be true
-    assert_eq!(3, go_cmd(start0, "up").curr_room);
-    // Going up from room 3 is not possible, so this should be true
-    assert_eq!(3, go_cmd(go_cmd(start1, "up"), "up").curr_room);
+    // Moving takes a turn, bumping into a wall doesn't.
+    let moved = go_cmd(start, "north");
+    assert_eq!(1, moved.turns);
+    assert_eq!(1, go_cmd(moved, "north").turns);
+}
+
+#[test]
+fn go_cmd_test_effects() {
+    let start = state::State::new(1,true,true,false,false
+                                  ,false,true,false,false);
+    // Walking into the smelly cell makes anyone nauseated.
+    let smelly = go_cmd(start, "east");
+    assert_eq!(room::SMELLY_CELL, smelly.curr_room);
+    assert!(status::has(&smelly, status::NAUSEATED));
+    // Nobody exhausted is going anywhere.
+    let tired = status::add(smelly, status::EXHAUSTED);
+    assert_eq!(room::SMELLY_CELL, go_cmd(tired, "west").curr_room);
+}
+
+#[test]
+fn go_cmd_test_final_door() {
+    let mut end = state::State::new(room::FINAL_ROOM,true,true,true,true
+                                   ,true,true,true,false);
+    end.turns = 12;
+    // Going north from the final room leaves the jail,
+    // which takes a turn like any other move.
+    let outside = go_cmd(end, "north");
+    assert_eq!(room::OUTSIDE, outside.curr_room);
+    assert_eq!(13, outside.turns);
+}
+
+/// Retraces the player's last move, whichever way it went.
+/// Going back takes a turn like any other move, and each step back
+/// goes one further, so the player can retrace their whole route.
+/// If the way back has since been blocked, or the player can't
+/// move, the player stays put and the step is still there to retrace.
+
+pub fn back_cmd(gstate: state::State) -> state::State {
+    let prev = match gstate.trail.last() {
+        Some(&prev) => prev,
+        None        => {
+            println!("\n\nYou haven't been anywhere to go back to.");
+            return gstate
+        }
+    };
+    let exit = gstate.map.exits_from(gstate.curr_room)
+                         .into_iter()
+                         .find(|e| e.to == prev)
+                         .cloned();
+    let exit = match exit {
+        Some(exit) => exit,
+        None       => {
+            println!("\n\nYou can't see a way back to {} from here.",
+                     gstate.map.name(prev));
+            return gstate
+        }
+    };
+    if !exit.lock.is_empty() && !gstate.has_opened(&exit.lock) {
+        println!("\n\nThe way back to {} is blocked now.",
+                 gstate.map.name(prev));
+        return gstate
+    }
+    let mut gstate = go_cmd(gstate, &exit.dir);
+    if gstate.curr_room == prev {
+        // Stepping back doesn't count as a new step to retrace,
+        // and the step it retraced is done with.
+        gstate.trail.pop();
+        gstate.trail.pop();
+    }
+    gstate
+}
+
+#[test]
+fn back_cmd_test() {
+    let start = state::State::new(room::SMELLY_CELL,true,true,false,false
+                                  ,false,true,false,false);
+    // Out of the smelly cell to the west, then back the same way.
+    let moved = go_cmd(go_cmd(start, "west"), "north");
+    assert_eq!(2, moved.curr_room);
+    let back = back_cmd(moved);
+    assert_eq!(1, back.curr_room);
+    let back = back_cmd(back);
+    assert_eq!(room::SMELLY_CELL, back.curr_room);
+    assert_eq!(4, back.turns);
+    // There's nothing left to retrace.
+    assert_eq!(room::SMELLY_CELL, back_cmd(back).curr_room);
+}
+
+#[test]
+fn back_cmd_test_exhausted() {
+    let start = state::State::new(2,false,false,false,false
+                                  ,false,false,false,false);
+    let tired = status::add(go_cmd(start, "north"), status::EXHAUSTED);
+    // Too tired to go back, but the way back isn't forgotten.
+    let stuck = back_cmd(tired);
+    assert_eq!(3, stuck.curr_room);
+    assert_eq!(vec![2], stuck.trail);
+}
+
+#[test]
+fn back_cmd_test_blocked() {
+    let mut start = state::State::new(0,false,true,false,false
+                                     ,false,false,false,false);
+    start.trail = vec![room::SECRET_ROOM];
+    // The wall hasn't been opened, so there's no way back through it.
+    assert_eq!(0, back_cmd(start).curr_room);
+}
+
+/// Works out the way to a room the player has already visited,
+/// picked by its name or part of it, over passages the player
+/// can get through. A room's full name beats a part of another's,
+/// and if the part given fits rooms with different names, the
+/// player is asked which one they mean. Of several rooms with
+/// the same name, the nearest one is picked.
+/// Moving happens one step at a time in main(),
+/// with each step taking a turn like any other move.
+///
+/// Returns the steps to take, as (direction, room it leads to)
+/// pairs, which are empty if there's nowhere to go.
+
+pub fn travel_cmd(gstate: &state::State, query: &str) -> Vec<(String, usize)> {
+    if query.is_empty() {
+        println!("\n\nTravel where?");
+        return Vec::new()
+    }
+    let name = |r: &usize| gstate.map.name(*r).to_ascii_lowercase();
+    let exact: Vec<usize> = gstate.visited.iter()
+                                          .cloned()
+                                          .filter(|r| name(r) == query)
+                                          .collect();
+    let found = if exact.is_empty() {
+        gstate.visited.iter()
+                      .cloned()
+                      .filter(|r| name(r).contains(query))
+                      .collect()
+    } else {
+        exact
+    };
+    let mut names: Vec<&str> = found.iter().map(|&r| gstate.map.name(r)).collect();
+    names.sort_unstable();
+    names.dedup();
+    match names.as_slice() {
+        []                => {
+            println!("\n\nYou don't know of anywhere called \"{}\".", query);
+            return Vec::new()
+        },
+        [_]               => {},
+        [rest @ .., last] => {
+            println!("\n\nDo you mean {} or {}?", rest.join(", "), last);
+            return Vec::new()
+        },
+    }
+    if found.contains(&gstate.curr_room) {
+        println!("\n\nYou're already there.");
+        return Vec::new()
+    }
+    let known = |e: &room::Exit| gstate.visited.contains(&e.to)
+                                 && (e.lock.is_empty()
+                                     || gstate.has_opened(&e.lock));
+    let nearest = found.iter()
+                       .filter_map(|&r| gstate.map.path(gstate.curr_room, r, &known))
+                       .min_by_key(|steps| steps.len());
+    match nearest {
+        Some(steps) => {
+            println!("\n\nYou set off for {}.", names[0]);
+            steps
+        },
+        None        => {
+            println!("\n\nYou don't know a way to {} from here.", names[0]);
+            Vec::new()
+        }
+    }
+}
+
+#[test]
+fn travel_cmd_test() {
+    let mut gstate = state::State::new(11,true,true,false,false
+                                      ,false,false,false,false);
+    gstate.visited = vec![0, 1, 2, 3, 9, 10, 11];
+    let steps = travel_cmd(&gstate, "old man");
+    assert_eq!(3, steps.len());
+    assert_eq!(("southwest".to_string(), 9), steps[0]);
+    // Nobody can travel somewhere they've never been.
+    assert!(travel_cmd(&gstate, "laundry").is_empty());
+    // 'storage' could be either storage room, so the player is asked.
+    gstate.curr_room = 0;
+    assert!(travel_cmd(&gstate, "storage").is_empty());
+    // But a room's full name beats a part of another's.
+    gstate.map.names.retain(|n| n.0 != 1);
+    gstate.map.names.push((1, "Cell".to_string()));
+    assert_eq!(vec![("north".to_string(), 1)], travel_cmd(&gstate, "cell"));
 }
 
 /// Allows player to closely examine key objects and effectively "take"
-/// key items when they are look at.
+/// key items when they are look at. Anything else is looked up in
+/// the scenery for the player's room and what they're carrying.
 /// 
 /// Returns a new game state with the updated player discoveries.
 /// If the desired object is not examinable, returns old state.
+/// Examining an object advances the turn counter.
 
-pub fn look_cmd(gstate: state::State, obj: &str) -> state::State {
+pub fn look_cmd(mut gstate: state::State, obj: &str,
+                scenery: &[scenery::Scenery]) -> state::State {
+    // Keys lying around a generated wing get picked up on sight.
+    if let Some(name) = wing::key_here(&gstate, obj) {
+        println!("\n\nYou pick up the {} key.", name);
+        gstate.keys.push(name);
+        return gstate.tick()
+    }
+    let room = gstate.curr_room;
     match obj {
-        "wall"  =>
-            {   // Only examine wall if player is in the right room.
-                match gstate.curr_room {
-                    room::CELL        => 
-                        {
-                            // If player already looked at wall,
-                            // display alternate description.
-                            if gstate.examined_wall {
-                                println!("\n\nYou see the entryway \
-                                          to the secret room.");
-                            } else {
-                                println!(
-                                "\n\nYou see a slight \
-                                 indentation in the wall.\n\
-                                 You put you hand against \
-                                 it and push gently.\n\
-                                 As soon as you apply the \
-                                 least bit of pressure,\n\
-                                 the indentation pushes inward, \
-                                 revealing\n\
-                                 a small, dimly lit room.");
-                                // set gstate.examined_wall to true
-                                return gstate.update((1, true));
-                            }
-                        }
-                    room::SECRET_ROOM =>
-                            println!("\n\nYou see the hole in \
-                                      the wall leading back into \
-                                      your cell..."),
-                    _                 =>
-                            println!("\n\nYou look carefully \
-                                      at each wall...\
-                                      for some reason."),
-                }
-                gstate
+        "wall" if room == room::CELL && !gstate.examined_wall =>
+            {
+                println!("\n\nYou see a slight \
+                          indentation in the wall.\n\
+                          You put you hand against \
+                          it and push gently.\n\
+                          As soon as you apply the \
+                          least bit of pressure,\n\
+                          the indentation pushes inward, \
+                          revealing\n\
+                          a small, dimly lit room.");
+                // set gstate.examined_wall to true
+                gstate.tick().update((1, true))
             }
-        "table" =>
-            { 
-                if gstate.curr_room == room::SECRET_ROOM {
-                    if gstate.took_key {
-                        println!("\n\nThe table is now empty...");
-                        gstate
-                    } else {
-                        println!("\n\nYou walk up to the table and look \
-                                  at it more closely.\nOn the table \
-                                  there is a key and nothing else.\n\
-                                  You take the key with trembling fingers.");
-                        // set gstate.took_key to true
-                        gstate.update((2, true))
-                    }
+        "table" | "key" if room == room::SECRET_ROOM
+                           && items::is_here(&gstate, items::KEY)
+                           && !items::is_moved(&gstate, items::KEY) =>
+            {
+                if obj == "table" {
+                    println!("\n\nYou walk up to the table and look \
+                              at it more closely.\nOn the table \
+                              there is a key and nothing else.\n\
+                              You take the key with trembling fingers.");
                 } else {
-                    println!("\n\nHmmm...there aren't \
-                              any tables around you...");
-                    gstate
+                    println!("\n\nOn the table there is a key \
+                              and nothing else.\n\
+                              You take the key with trembling fingers.");
                 }
+                // set gstate.took_key to true
+                gstate.tick().update((2, true))
             }
-        "broom" =>
-            { 
-                if gstate.curr_room == room::BROOM_ROOM {
-                    if gstate.took_broom {
-                        println!("\n\nIt's a broom...");
-                        gstate
-                    } else {
-                        println!("\n\nYou walk closer to the broom and \
-                                  take a closer look at it.\n\
-                                  Ironically, it's filthy.\n\
-                                  Despite this, you take the broom.");
-                        // set gstate.took_broom to true
-                        gstate.update((3, true))
-                    }
-                } else {
-                    println!("\n\nThere are no brooms in sight...");
-                    gstate
-                }
+        "key" if items::is_here(&gstate, items::KEY) =>
+            {
+                println!("\n\nYou pick up the small key.\n\
+                          It looks like it might fit \
+                          your cell door.");
+                // set gstate.took_key to true
+                gstate.tick().update((2, true))
             }
-        "nail"  =>
-            { 
-                if gstate.curr_room == room::SMELLY_CELL {
-                    if gstate.took_nail {
-                        println!("\n\nIt's a bent, jagged nail.");
-                        gstate
-                    } else {
-                        println!("\n\nYou approach the cot.\n\
-                                  The closer you get, \
-                                  the stronger the scent.\n\
-                                  You quickly grab the nail, \
-                                  without touching the cot itself,\n\
-                                  and move swiftly away from the bed.");
-                        // set gstate.took_nail to true
-                        gstate.update((5, true))
-                    }
+        "broom" if items::is_here(&gstate, items::BROOM) =>
+            {
+                if items::is_moved(&gstate, items::BROOM) {
+                    println!("\n\nYou pick up the broom.\n\
+                              Ironically, it's filthy.");
                 } else {
-                    println!("\n\nThere are no brooms in sight...");
-                    gstate
+                    println!("\n\nYou walk closer to the broom and \
+                              take a closer look at it.\n\
+                              Ironically, it's filthy.\n\
+                              Despite this, you take the broom.");
                 }
+                // set gstate.took_broom to true
+                gstate.tick().update((3, true))
             }
-        "clothes"  =>
-            { 
-                if gstate.curr_room == room::FINAL_ROOM {
-                    if gstate.wearing_clothes {
-                        println!("\n\nMore uniforms lay in piles.");
-                    } else {
-                        println!("\n\nYou take a closer look \
-                                  at the clothes scattered around.\n\
-                                  You soon realize that they are \
-                                  uniforms.\nThinking back, you seem \
-                                  to recall the old man from \
-                                  earlier wearing the same uniform.\n\
-                                  You decide to find one that fits \
-                                  and put it on...for safe measure.");
-                        // set gstate.took_nail to true
-                        return gstate.update((8, true));
-                    }
-                } else if gstate.wearing_clothes {
-                    println!("\n\nYou are wearing what you can only \
-                              describe as a janitor's uniform.");
+        "nail" if items::is_here(&gstate, items::NAIL) =>
+            {
+                if items::is_moved(&gstate, items::NAIL) {
+                    println!("\n\nYou pick up the long, \
+                              crooked nail.");
                 } else {
-                    println!("\n\nYou are wearing tattered clothes \
-                              that you don't remember putting on...");
+                    println!("\n\nYou approach the cot.\n\
+                              The closer you get, \
+                              the stronger the scent.\n\
+                              You quickly grab the nail, \
+                              without touching the cot itself,\n\
+                              and move swiftly away from the bed.");
                 }
-                gstate
+                // set gstate.took_nail to true
+                gstate.tick().update((5, true))
             }
-        "self"  =>
+        "clothes" | "uniforms" if room == room::FINAL_ROOM
+                                  && !gstate.wearing_clothes =>
             {
-                println!("\n\nThat's a little vain, isn't it?");
-                gstate
+                println!("\n\nYou take a closer look \
+                          at the clothes scattered around.\n\
+                          You soon realize that they are \
+                          uniforms.\nThinking back, you seem \
+                          to recall the old man from \
+                          earlier wearing the same uniform.\n\
+                          You decide to find one that fits \
+                          and put it on...for safe measure.");
+                // set gstate.wearing_clothes to true
+                gstate.tick().update((8, true))
             }
         other   =>
-            {
-                println!("\n\nCan't examine {}.", other);
-                gstate
+            match scenery::resolve(scenery, &gstate, other) {
+                Some(thing) =>
+                    {
+                        println!("\n\n{}", scenery::describe(thing, &gstate));
+                        gstate.tick()
+                    }
+                None        =>
+                    {
+                        println!("\n\nYou don't see any {} here.", other);
+                        gstate
+                    }
             }
     }
 }
 
+#[test]
+fn look_cmd_test_shuffled() {
+    let mut start = state::State::new(0,false,false,false,false
+                                     ,false,false,false,false);
+    start.key_room = room::CELL;
+    // With the key moved into the cell, it can be picked up there,
+    let taken = look_cmd(start, "key", &[]);
+    assert!(taken.took_key);
+    // but not from the table it usually sits on.
+    let mut secret = taken.clone();
+    secret.curr_room = room::SECRET_ROOM;
+    secret.took_key = false;
+    assert!(!look_cmd(secret, "table", &[]).took_key);
+}
+
 #[test]
 fn look_cmd_test_ok() {
     let start = state::State::new(0,false,false,false,false
                                   ,false,false,false,false);
     // Looking at wall in room 0 should set examined_wall to true,
     // so this should be true
-    assert!(look_cmd(start, "wall").examined_wall);
+    assert!(look_cmd(start, "wall", &[]).examined_wall);
+}
+
+#[test]
+fn look_cmd_test_scenery() {
+    let scenery = scenery::parse(&helpers::blocks("has nail\nnail\n\
+                                                   It's a bent, jagged nail.\n"));
+    let start = state::State::new(1,true,true,false,false
+                                  ,false,false,false,false);
+    // Nothing to see until the nail's been picked up,
+    assert_eq!(0, look_cmd(start.clone(), "nail", &scenery).turns);
+    // and then it can be looked at anywhere.
+    let carrying = state::State { took_nail: true, ..start };
+    assert_eq!(1, look_cmd(carrying, "nail", &scenery).turns);
 }
 
 #[test]
 #[should_panic]
 fn look_cmd_test_fail() {
-    let start = state::State::new(0,false,false,false,false
+    let start = state::State::new(1,false,false,false,false
                                   ,false,false,false,false);
     // Looking at wall in room 1 should set not affect anything,
     // so this should be false and cause panic
-    assert!(look_cmd(start, "wall").examined_wall);
+    assert!(look_cmd(start, "wall", &[]).examined_wall);
 }
 
 /// Allows player to communicate with whatever NPC is in the same room.
@@ -342,12 +1122,24 @@ fn look_cmd_test_fail() {
 ///
 /// Takes a pair containing a vec of NPCs and a gamestate,
 /// updates them if necessary, and the returns the same pair type.
+/// Talking to someone advances the turn counter.
+/// A nauseated player can't talk to anyone.
 
 pub fn talk_cmd(mut npcs: Vec<npcs::Npc>,
                 gstate: state::State) -> (Vec<npcs::Npc>, state::State) {
+    if status::has(&gstate, status::NAUSEATED) {
+        println!("\n\nYou open your mouth to speak, \
+                  but it's all you can do not to gag.");
+        return (npcs, gstate)
+    }
+    let gstate = if npcs.iter().any(|n| n.location == gstate.curr_room) {
+        gstate.tick()
+    } else {
+        gstate
+    };
     // Case for NPC named Carl
     if gstate.curr_room == npcs[0].location {
-        if gstate.took_broom && npcs[0].given_quest_item == false {
+        if gstate.took_broom && !npcs[0].given_quest_item {
             npcs[0] = npcs[0].receive_item().speak();
             // set gstate.helped_carl to true
             return (npcs, gstate.update((4, true)))
@@ -356,8 +1148,9 @@ pub fn talk_cmd(mut npcs: Vec<npcs::Npc>,
         }
     // Case for NPC named Blimpo
     } else if gstate.curr_room == npcs[1].location {
-        if gstate.took_nail && npcs[1].given_quest_item == false {
-            npcs[1] = npcs[1].receive_item().speak();
+        if gstate.took_nail && !npcs[1].given_quest_item {
+            // He's through the final door as soon as it's open.
+            npcs[1] = npcs[1].receive_item().speak().leave();
             // set gstate.final_room_unlocked to true
             return (npcs, gstate.update((7, true)))
         } else if npcs[1].quest_done {
@@ -374,28 +1167,54 @@ pub fn talk_cmd(mut npcs: Vec<npcs::Npc>,
 
 // TODO: Unit tests for talk_cmd
 
+/// Displays the turn count, the in-game time,
+/// and the player's score so far, less what hints cost.
+
+pub fn score_cmd(gstate: &state::State) {
+    println!("\n\nIt is turn {}, and the time is {}.",
+             gstate.turns, gstate.clock());
+    println!("Your score is {} out of a possible {}.",
+             gstate.current_score(), state::MAX_SCORE);
+    let taken = gstate.hints.len();
+    if taken > 0 {
+        println!("You've taken {} hint{}, costing you {} points.",
+                 taken, if taken == 1 { "" } else { "s" }, gstate.hint_penalty());
+    }
+}
+
 /// Uses a serialized version of the game state,
-/// combined with a serialized version of the NPC
-/// vec to save the current game to a file.
+/// combined with serialized versions of the NPC
+/// and enemy vecs to save the current game to a file.
 ///
 /// Can return file write error.
 
-pub fn save_game(gs: String, npcs: String) {
+pub fn save_game(gs: String, npcs: String, enemies: String) {
+    fs::write(SAVE_PATH, save_data(gs, npcs, enemies))
+        .expect("Error writing save file");
+}
+
+/// Joins the serialized pieces of a game into
+/// the text that gets written to the save file.
+
+pub fn save_data(gs: String, npcs: String, enemies: String) -> String {
     let mut data = gs;
     data.push_str(&npcs);
-    fs::write(SAVE_PATH, &data).expect("Error writing save file");
+    data.push('~');
+    data.push_str(&enemies);
+    data
 }
 
 /// Deserializes the save file in the supplied path
-/// and then populates the game state and NPC vec with
-/// the info found, and returns a pair type (Vec<Npc>, State).
+/// and then populates the game state, NPC vec and enemy vec
+/// with the info found, and returns a triple (State, Vec<Npc>, Vec<Enemy>).
 
-pub fn load_game() -> (state::State, Vec<npcs::Npc>) {
+pub fn load_game() -> (state::State, Vec<npcs::Npc>, Vec<combat::Enemy>) {
     let data = fs::read_to_string(SAVE_PATH).unwrap();
-    // Split on '~' separates State from NPC info
+    // Split on '~' separates State from NPC info from enemy info
     let split_data: Vec<&str> = data.as_str().split("~").collect();
     ( state::State::deserialize(split_data[0]),
-      npcs::deserialize(split_data[1]) )
+      npcs::deserialize(split_data[1]),
+      combat::deserialize(split_data[2]) )
 }
 
 /// When the player leaves through the northern door of
@@ -404,24 +1223,120 @@ pub fn load_game() -> (state::State, Vec<npcs::Npc>) {
 /// Depending on the decisions/actions of the player,
 /// either a bad or good ending plays out.
 ///
-/// Displays ending text and then erases the
-/// player's save data and exits the program/game.
+/// Displays ending text, archives the finished run, records the
+/// ending and any achievements in the player's profile, then lets the player
+/// rewind to before the final door, start a new game+, or quit.
+/// The save is kept as it was just before the final door.
 /// Doesn't touch user input history.
 
-pub fn game_end(helped_carl: bool, wearing_clothes: bool) {
-    if wearing_clothes {
-        helpers::print_from_file(GOOD_ENDING0_PATH);
-    } else if helped_carl {
-        helpers::print_from_file(GOOD_ENDING1_PATH);
-    } else {
-        helpers::print_from_file(BAD_ENDING_PATH);
+pub fn game_end(gstate: state::State, npcs: Vec<npcs::Npc>,
+                enemies: Vec<combat::Enemy>)
+                -> (state::State, Vec<npcs::Npc>, Vec<combat::Enemy>) {
+    let ending = ending_id(&gstate);
+    match ending {
+        "good0" => helpers::print_from_file(GOOD_ENDING0_PATH),
+        "good1" => helpers::print_from_file(GOOD_ENDING1_PATH),
+        "wing"  => helpers::print_from_file(WING_ENDING_PATH),
+        _       => helpers::print_from_file(BAD_ENDING_PATH),
     }
+    println!("\n\nEnding: {}", profile::ending_title(ending));
+    println!("Your run lasted {} turns, ending at {}.",
+             gstate.turns, gstate.clock());
+    // Only escaping is worth the escape points.
+    let final_score = gstate.final_score(ending != "bad");
+    println!("Final score: {} out of a possible {}.",
+             final_score, state::MAX_SCORE);
+    // Everything just as it was before the player went through the door.
+    let door_room = gstate.map.exits.iter()
+                                    .find(|e| e.to == room::OUTSIDE)
+                                    .map(|e| e.from)
+                                    .unwrap_or(room::FINAL_ROOM);
+    let mut before_door = state::State { curr_room: door_room, ..gstate };
+    // Going through the door isn't a step to retrace anymore.
+    if before_door.trail.last() == Some(&door_room) {
+        before_door.trail.pop();
+    }
+    let save_data = save_data(before_door.serialize(),
+                              npcs::serialize(&npcs),
+                              combat::serialize(&enemies));
+    fs::write(SAVE_PATH, &save_data).expect("Error writing save file");
+    helpers::append_to_file(RUNS_PATH,
+                            &format!("ending: {}\nturns: {}\nscore: {}\n\
+                                      {}\n=====\n",
+                                     ending, before_door.turns,
+                                     final_score, save_data));
+    let mut prof = profile::load(PROFILE_PATH);
+    prof.unlock_ending(ending);
+    for id in prof.award_achievements(ending, &before_door, &npcs) {
+        println!("\nAchievement unlocked: {}", profile::achievement_title(id));
+    }
+    profile::save(PROFILE_PATH, &prof);
     helpers::wait_for_player();
-    let npcs_default = fs::read_to_string(NPCS_DEF).unwrap();
-    let state_default = fs::read_to_string(STATE_DEF).unwrap();
-    // Save a default game for safety when player chooses 'continue' at menu.
-    save_game(state_default, npcs_default);
-    process::exit(1);
+    loop {
+        helpers::print_from_file(END_MENU_PATH);
+        match helpers::read_choice("\n> ").as_str() {
+            "rewind" | "r"            =>
+                {
+                    println!("\n\nYou find yourself back in front \
+                              of the door marked \"EXIT\"...");
+                    return (before_door, npcs, enemies)
+                },
+            "new+" | "ng+" | "n"      =>
+                {
+                    let mut fresh = state::State::empty();
+                    fresh.ng_plus = before_door.ng_plus + 1;
+                    // Keep drawing from the same rng, so the whole
+                    // session can still be replayed from its seed.
+                    fresh.rng = before_door.rng;
+                    fresh.verbosity = before_door.verbosity;
+                    if before_door.mode == "shuffle" {
+                        fresh = items::shuffle(fresh);
+                    } else if before_door.mode == "wing" {
+                        fresh = wing::new_game(fresh);
+                    }
+                    println!("\n\nNew Game+ {}!", fresh.ng_plus);
+                    helpers::print_from_file(INTRO_PATH);
+                    return (fresh, npcs::generate_npcs(),
+                            combat::generate_enemies())
+                },
+            "quit" | "q"              =>
+                {
+                    println!("\n\nExiting!");
+                    process::exit(1)
+                },
+            _                         => println!("\nInvalid choice!"),
+        }
+    }
+}
+
+/// Works out which ending the player has earned,
+/// based on the decisions they made during the run.
+/// Returns the ending's id from profile::ENDINGS.
+
+pub fn ending_id(gstate: &state::State) -> &'static str {
+    if gstate.mode == "wing" {
+        "wing"
+    } else if gstate.wearing_clothes {
+        "good0"
+    } else if gstate.helped_carl {
+        "good1"
+    } else {
+        "bad"
+    }
+}
+
+#[test]
+fn ending_id_test() {
+    let disguised = state::State::new(room::FINAL_ROOM,true,true,true,true
+                                     ,true,true,true,true);
+    let helpful = state::State::new(room::FINAL_ROOM,true,true,true,true
+                                   ,true,true,true,false);
+    let neither = state::State::new(room::FINAL_ROOM,true,true,false,false
+                                   ,true,true,true,false);
+    assert_eq!("good0", ending_id(&disguised));
+    assert_eq!("good1", ending_id(&helpful));
+    assert_eq!("bad", ending_id(&neither));
+    assert_eq!("wing", ending_id(&wing::new_game(neither)));
 }
 
 
@@ -9,6 +9,7 @@ pub struct Npc {
     pub has_been_met: bool,
     pub given_quest_item: bool,
     pub quest_done: bool,
+    pub gone: bool,
     pub monologue_intro: String,
     pub monologue_neutral: String,
     pub monologue_ending: String,
@@ -35,6 +36,7 @@ impl Npc {
             has_been_met: false,
             given_quest_item: false,
             quest_done: false,
+            gone: false,
             monologue_intro,
             monologue_neutral,
             monologue_ending,
@@ -46,11 +48,13 @@ impl Npc {
     /// Meant to create a new Npc that is an updated version
     /// of an existing Npc.
 
+    #[allow(clippy::too_many_arguments)]
     pub fn update(
         name_ref: &str,
         has_been_met: bool,
         given_quest_item: bool,
         quest_done: bool,
+        gone: bool,
         intro_ref: &str,
         neutral_ref: &str,
         ending_ref: &str,
@@ -62,6 +66,7 @@ impl Npc {
             has_been_met,
             given_quest_item,
             quest_done,
+            gone,
             monologue_intro: intro_ref.to_string(),
             monologue_neutral: neutral_ref.to_string(),
             monologue_ending: ending_ref.to_string(),
@@ -86,7 +91,9 @@ impl Npc {
             monologue_neutral: entries.next().unwrap().parse().unwrap(),
             monologue_ending: entries.next().unwrap().parse().unwrap(),
             monologue_done: entries.next().unwrap().parse().unwrap(),
-            location: entries.next().unwrap().parse().unwrap()
+            location: entries.next().unwrap().parse().unwrap(),
+            // Saves from before anyone could leave don't say.
+            gone: entries.next() == Some("true"),
         }
     }
 
@@ -95,7 +102,7 @@ impl Npc {
     pub fn serialize(&self) -> String {
         let s = format!(
             // The individual Npc delimiter is '^'
-            "\n^\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}"
+            "\n^\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}"
             , &self.name.to_string()
             , &self.has_been_met.to_string()
             , &self.given_quest_item.to_string()
@@ -105,6 +112,7 @@ impl Npc {
             , &self.monologue_ending.to_string()
             , &self.monologue_done.to_string()
             , &self.location.to_string()
+            , &self.gone.to_string()
                        );
         s
     }
@@ -118,6 +126,7 @@ impl Npc {
             true,
             self.given_quest_item,
             self.quest_done,
+            self.gone,
             &self.monologue_intro,
             &self.monologue_neutral,
             &self.monologue_ending,
@@ -137,6 +146,7 @@ impl Npc {
             self.has_been_met,
             true,
             self.quest_done,
+            self.gone,
             &self.monologue_intro,
             &self.monologue_neutral,
             &self.monologue_ending,
@@ -157,6 +167,7 @@ impl Npc {
             self.has_been_met,
             self.given_quest_item,
             true,
+            self.gone,
             &self.monologue_intro,
             &self.monologue_neutral,
             &self.monologue_ending,
@@ -165,6 +176,32 @@ impl Npc {
         )
     }
 
+    /// Sets the gone field to true.
+    /// Tracks whether the Npc has left for good, after which
+    /// there's no sign of them where they used to be.
+
+    pub fn leave(&self) -> Npc {
+        Npc::update(
+            &self.name,
+            self.has_been_met,
+            self.given_quest_item,
+            self.quest_done,
+            true,
+            &self.monologue_intro,
+            &self.monologue_neutral,
+            &self.monologue_ending,
+            &self.monologue_done,
+            self.location,
+        )
+    }
+
+    /// Whether the Npc can be found in the given room,
+    /// which they can't once they've left.
+
+    pub fn is_in(&self, room: usize) -> bool {
+        !self.gone && self.location == room
+    }
+
     /// When the player talks to an Npc, this function is called
     /// and, based off of various boolean fields in the Npc object,
     /// displays 1 of 4 possible monologues, then updates said booleans
@@ -243,7 +280,7 @@ pub fn deserialize(data: &str) -> Vec<Npc> {
     let mut npcs = Vec::new();
     for npc in npc_data {
         // Don't try to load an empty string.
-        if npc.is_empty() == false {
+        if !npc.is_empty() {
             // Load and push an NPC onto the vec.
             npcs.push(Npc::deserialize(npc));
         }
@@ -253,7 +290,7 @@ pub fn deserialize(data: &str) -> Vec<Npc> {
 
 /// Converts each Npc in an Vec<Npc> into a String representation for saving.
 
-pub fn serialize(npcs: Vec<Npc>) -> String {
+pub fn serialize(npcs: &[Npc]) -> String {
     let mut npc_data = String::new();
     for npc in npcs { npc_data.push_str(&npc.serialize()) };
     npc_data
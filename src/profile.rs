@@ -0,0 +1,233 @@
+use std::fs;
+use crate::state::State;
+use crate::npcs::Npc;
+
+/// Every ending the game can play out, as (id, title) pairs.
+/// The id is what gets recorded in the profile and run archive.
+
+pub const ENDINGS: [(&str, &str); 4] = [
+    ("bad",   "Back Inside"),
+    ("good0", "Reporting For Duty"),
+    ("good1", "The New Janitor"),
+    ("wing",  "Out Of The Wing"),
+];
+
+/// Every achievement the player can earn, as (id, title, description).
+/// Achievements are checked when a run ends.
+
+pub const ACHIEVEMENTS: [(&str, &str, &str); 5] = [
+    ("disguised",   "Dressed For The Part",
+                    "Escape wearing the uniform."),
+    ("good_deed",   "Clean Conscience",
+                    "Bring Carl his broom before escaping."),
+    ("loner",       "Never Talked To Carl",
+                    "Escape without ever speaking to Carl."),
+    ("quick",       "In And Out",
+                    "Escape in 30 turns or fewer."),
+    ("completist",  "Seen It All",
+                    "See every ending."),
+];
+
+/// Looks up the title of the ending with the given id.
+
+pub fn ending_title(id: &str) -> &'static str {
+    match ENDINGS.iter().find(|e| e.0 == id) {
+        Some(e) => e.1,
+        None    => "???",
+    }
+}
+
+/// Represents everything that outlives a single playthrough,
+/// such as the endings the player has seen so far.
+
+pub struct Profile {
+    pub endings: Vec<String>,
+    pub achievements: Vec<String>,
+}
+
+/// The implementation of the Profile struct.
+
+impl Profile {
+
+    /// Create a profile with nothing unlocked yet.
+
+    pub fn empty() -> Profile {
+        Profile {
+            endings: Vec::new(),
+            achievements: Vec::new(),
+        }
+    }
+
+    /// Takes the contents of the profile file and
+    /// returns the profile that can be built from it.
+    /// Each line holds a comma separated list.
+
+    pub fn deserialize(data: &str) -> Profile {
+        let mut entries = data.lines();
+        Profile {
+            endings: split_list(entries.next()),
+            achievements: split_list(entries.next()),
+        }
+    }
+
+    /// Converts a Profile object into a String representation for saving.
+
+    pub fn serialize(&self) -> String {
+        format!("{}\n{}\n", self.endings.join(","), self.achievements.join(","))
+    }
+
+    /// Records that the player has seen the given ending.
+    /// Seeing the same ending twice doesn't change anything.
+
+    pub fn unlock_ending(&mut self, id: &str) {
+        if !self.endings.iter().any(|e| e == id) {
+            self.endings.push(id.to_string());
+        }
+    }
+
+    /// Records that the player has earned the given achievement.
+    /// Returns whether it was newly earned.
+
+    pub fn unlock_achievement(&mut self, id: &str) -> bool {
+        if self.achievements.iter().any(|a| a == id) {
+            false
+        } else {
+            self.achievements.push(id.to_string());
+            true
+        }
+    }
+
+    /// Checks the run that finished with the given ending for
+    /// achievements and records any that were earned. Should be
+    /// called after the run's ending has been unlocked. Getting
+    /// caught doesn't count as escaping, however it was done.
+    ///
+    /// Returns the ids of the achievements earned for the first time.
+
+    pub fn award_achievements(&mut self, ending: &str, gstate: &State,
+                              npcs: &[Npc]) -> Vec<&'static str> {
+        let escaped = ending != "bad";
+        let met_carl = npcs.iter().any(|n| n.name == "Carl" && n.has_been_met);
+        // Carl is only ever in the classic jail, not in generated wings.
+        let carl_around = gstate.mode != "wing";
+        let seen_all = ENDINGS.iter()
+                              .all(|e| self.endings.iter().any(|s| s == e.0));
+        let mut earned = Vec::new();
+        for &(id, _, _) in ACHIEVEMENTS.iter() {
+            let done = match id {
+                "disguised"  => escaped && gstate.wearing_clothes,
+                "good_deed"  => escaped && gstate.helped_carl,
+                "loner"      => escaped && carl_around && !met_carl,
+                "quick"      => escaped && gstate.turns <= 30,
+                "completist" => seen_all,
+                _            => false,
+            };
+            if done && self.unlock_achievement(id) {
+                earned.push(id);
+            }
+        }
+        earned
+    }
+}
+
+/// Looks up the title of the achievement with the given id.
+
+pub fn achievement_title(id: &str) -> &'static str {
+    match ACHIEVEMENTS.iter().find(|a| a.0 == id) {
+        Some(a) => a.1,
+        None    => "???",
+    }
+}
+
+/// Displays every ending and achievement to the player.
+/// Anything not yet unlocked is shown as a locked placeholder.
+
+pub fn show_gallery(profile: &Profile) {
+    println!("\n\n---------------------------------\n");
+    println!("          ___Endings___\n");
+    for &(id, title) in ENDINGS.iter() {
+        if profile.endings.iter().any(|e| e == id) {
+            println!("  * {}", title);
+        } else {
+            println!("  * ??? (locked)");
+        }
+    }
+    println!("\n        ___Achievements___\n");
+    for &(id, title, desc) in ACHIEVEMENTS.iter() {
+        if profile.achievements.iter().any(|a| a == id) {
+            println!("  * {} - {}", title, desc);
+        } else {
+            println!("  * ??? (locked)");
+        }
+    }
+    println!("\n---------------------------------");
+}
+
+/// Splits a comma separated line into its non-empty entries.
+
+fn split_list(line: Option<&str>) -> Vec<String> {
+    match line {
+        Some(l) => l.split(',')
+                    .filter(|e| !e.is_empty())
+                    .map(|e| e.to_string())
+                    .collect(),
+        None    => Vec::new(),
+    }
+}
+
+/// Loads the profile at the supplied path.
+/// A missing profile just means nothing has been unlocked yet.
+
+pub fn load(path: &str) -> Profile {
+    match fs::read_to_string(path) {
+        Ok(data) => Profile::deserialize(&data),
+        Err(_)   => Profile::empty(),
+    }
+}
+
+/// Writes the profile to the supplied path.
+///
+/// Can return file write error.
+
+pub fn save(path: &str, profile: &Profile) {
+    fs::write(path, profile.serialize()).expect("Error writing profile");
+}
+
+#[test]
+fn profile_round_trip_test() {
+    let mut profile = Profile::empty();
+    profile.unlock_ending("good0");
+    profile.unlock_ending("bad");
+    profile.unlock_ending("good0");
+    let loaded = Profile::deserialize(&profile.serialize());
+    assert_eq!(vec!["good0", "bad"], loaded.endings);
+    assert!(loaded.achievements.is_empty());
+}
+
+#[test]
+fn award_achievements_test() {
+    let mut profile = Profile::empty();
+    let mut gstate = State::new(7,true,true,true,true,true,true,true,true);
+    gstate.turns = 50;
+    profile.unlock_ending("good0");
+    let earned = profile.award_achievements("good0", &gstate, &[]);
+    // Carl was never met, but the run was too slow to be quick.
+    assert_eq!(vec!["disguised", "good_deed", "loner"], earned);
+    // Nothing is awarded twice.
+    assert!(profile.award_achievements("good0", &gstate, &[]).is_empty());
+    // There's no Carl to avoid in a generated wing.
+    let mut profile = Profile::empty();
+    gstate.mode = "wing".to_string();
+    assert_eq!(vec!["disguised", "good_deed"],
+               profile.award_achievements("wing", &gstate, &[]));
+}
+
+#[test]
+fn award_achievements_test_caught() {
+    let mut profile = Profile::empty();
+    let mut gstate = State::new(7,true,true,false,false,true,true,true,false);
+    gstate.turns = 20;
+    profile.unlock_ending("bad");
+    // Quick and never near Carl, but getting caught isn't escaping.
+    assert!(profile.award_achievements("bad", &gstate, &[]).is_empty());
+}
@@ -0,0 +1,127 @@
+use crate::combat::Enemy;
+use crate::npcs::Npc;
+
+/// Represents what the player last talked about, so that
+/// commands can refer back to it as 'it', 'them', 'him' or 'her'.
+
+#[derive(Default)]
+pub struct Referents {
+    pub thing: Option<String>,
+    pub person: Option<String>,
+}
+
+/// The implementation of the Referents struct.
+
+impl Referents {
+
+    /// Remembers a thing the player has just referred to.
+
+    pub fn note_thing(self, thing: &str) -> Referents {
+        Referents { thing: Some(thing.to_string()), ..self }
+    }
+
+    /// Remembers a person the player has just referred to.
+
+    pub fn note_person(self, person: &str) -> Referents {
+        Referents { person: Some(person.to_string()), ..self }
+    }
+}
+
+/// Works out what a pronoun stands for. 'them' means a thing
+/// that comes in a bunch, like clothes, or else a person.
+///
+/// Returns None for words that aren't pronouns, and
+/// a question for the player if it isn't clear what's meant.
+
+fn resolve(refs: &Referents, word: &str) -> Option<Result<String, String>> {
+    let unclear = |word: &str| Err(format!("What do you mean by \"{}\"?", word));
+    let plural = refs.thing.as_ref().filter(|t| t.ends_with('s'));
+    let resolved = match word {
+        "it"          => refs.thing.clone().map_or(unclear(word), Ok),
+        "him" | "her" => refs.person.clone().map_or(unclear(word), Ok),
+        "them"        => match (plural, &refs.person) {
+            (Some(thing), Some(person)) =>
+                Err(format!("Do you mean the {} or {}?", thing, person)),
+            (Some(thing), None)         => Ok(thing.clone()),
+            (None, Some(person))        => Ok(person.clone()),
+            (None, None)                => unclear(word),
+        },
+        _             => return None,
+    };
+    Some(resolved)
+}
+
+/// Swaps any pronouns after the command word for whatever they
+/// stand for, so 'look at it' becomes 'look at key'.
+///
+/// Returns the command as it should be carried out,
+/// or a question for the player if it isn't clear what's meant.
+
+pub fn substitute(refs: &Referents, input: &str) -> Result<String, String> {
+    let mut words = Vec::new();
+    for (i, word) in input.split_whitespace().enumerate() {
+        match resolve(refs, &word.to_ascii_lowercase()) {
+            Some(resolved) if i > 0 => words.push(resolved?),
+            _                       => words.push(word.to_string()),
+        }
+    }
+    Ok(words.join(" "))
+}
+
+/// Remembers who and what the player referred to with their last
+/// command: whatever they looked at, and whoever they talked to or
+/// fought in the room they were in. Commands go by their own names.
+/// A command that didn't work doesn't change anything, so looking
+/// for something that isn't there doesn't make it 'it'.
+
+pub fn note(refs: Referents, cmd: &str, arg: &str, worked: bool,
+            room: usize, npcs: &[Npc], enemies: &[Enemy]) -> Referents {
+    if !worked {
+        return refs
+    }
+    let present = |name: &str, location: usize| {
+        location == room && (arg.is_empty() || name.eq_ignore_ascii_case(arg))
+    };
+    match cmd {
+        "look" if !arg.is_empty() =>
+            match npcs.iter().find(|n| n.name.eq_ignore_ascii_case(arg)) {
+                Some(npc) => refs.note_person(&npc.name),
+                None      => refs.note_thing(arg),
+            },
+        "talk"                    =>
+            match npcs.iter().find(|n| n.is_in(room)) {
+                Some(npc) => refs.note_person(&npc.name),
+                None      => refs,
+            },
+        "attack"                  =>
+            match enemies.iter().find(|e| present(&e.name, e.location)) {
+                Some(enemy) => refs.note_person(&enemy.name),
+                None        => refs,
+            },
+        _                         => refs,
+    }
+}
+
+#[test]
+fn substitute_test() {
+    let refs = Referents::default();
+    assert_eq!(Ok("look at wall".to_string()), substitute(&refs, "look at wall"));
+    assert!(substitute(&refs, "look at it").is_err());
+    let refs = refs.note_thing("key").note_person("Carl");
+    assert_eq!(Ok("look at key".to_string()), substitute(&refs, "look at It"));
+    assert_eq!(Ok("give Carl the broom".to_string()),
+               substitute(&refs, "give him the broom"));
+    assert_eq!(Ok("talk to Carl".to_string()), substitute(&refs, "talk to them"));
+    // Clothes and Carl could both be 'them', so the player gets asked.
+    let refs = refs.note_thing("clothes");
+    assert_eq!(Err("Do you mean the clothes or Carl?".to_string()),
+               substitute(&refs, "look at them"));
+}
+
+#[test]
+fn note_test() {
+    let refs = note(Referents::default(), "look", "wal", false, 0, &[], &[]);
+    assert!(refs.thing.is_none());
+    let refs = note(refs, "look", "wall", true, 0, &[], &[]);
+    assert_eq!(Some("wall".to_string()), refs.thing);
+}
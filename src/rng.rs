@@ -0,0 +1,75 @@
+/// A small seeded pseudo-random number generator (SplitMix64).
+///
+/// The game state owns the only one of these, and everything
+/// random in the game draws from it, so the same seed always
+/// plays out the same way. The seed and the number of draws
+/// made so far are enough to pick up exactly where it left off.
+
+#[derive(Clone)]
+pub struct Rng {
+    pub seed: u64,
+    pub draws: u64,
+    state: u64,
+}
+
+/// Implementation of Rng struct.
+
+impl Rng {
+
+    /// Create a new Rng that hasn't been drawn from yet.
+
+    pub fn new(seed: u64) -> Rng {
+        Rng { seed, draws: 0, state: seed }
+    }
+
+    /// Recreate an Rng that has already been drawn from
+    /// the given number of times, such as one from a save.
+
+    pub fn resume(seed: u64, draws: u64) -> Rng {
+        let mut rng = Rng::new(seed);
+        for _ in 0..draws { rng.next_u64(); }
+        rng
+    }
+
+    /// Draws the next raw 64-bit value.
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.draws += 1;
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Draws a number from low up to, but not including, high.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the range is empty.
+
+    pub fn range(&mut self, low: u32, high: u32) -> u32 {
+        assert!(low < high, "empty range");
+        low + (self.next_u64() % u64::from(high - low)) as u32
+    }
+}
+
+#[test]
+fn rng_same_seed_test() {
+    let mut a = Rng::new(42);
+    let mut b = Rng::new(42);
+    for _ in 0..100 {
+        let n = a.range(3, 9);
+        assert_eq!(n, b.range(3, 9));
+        assert!((3..9).contains(&n));
+    }
+}
+
+#[test]
+fn rng_resume_test() {
+    let mut original = Rng::new(1234);
+    for _ in 0..10 { original.next_u64(); }
+    let mut resumed = Rng::resume(original.seed, original.draws);
+    assert_eq!(original.next_u64(), resumed.next_u64());
+    assert_eq!(11, resumed.draws);
+}
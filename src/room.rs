@@ -1,5 +1,4 @@
 
-use crate::helpers::print_from_file;
 
 // Constants that denote various essential room numbers.
 pub const FINAL_ROOM: usize = 7;
@@ -7,6 +6,310 @@ pub const CELL: usize = 0;
 pub const SECRET_ROOM: usize = 12;
 pub const BROOM_ROOM: usize = 11;
 pub const SMELLY_CELL: usize = 8;
+// Not a real room; the player is here once they leave the jail.
+pub const OUTSIDE: usize = 99;
+
+// Locks that can keep a passage shut, and what opens them.
+// A passage behind LOCK_WALL is a secret until it's been opened.
+pub const LOCK_KEY: &str    = "key";    // The player has the cell key
+pub const LOCK_WALL: &str   = "wall";   // The player found the secret room
+pub const LOCK_STENCH: &str = "stench"; // The player has a reason to go in
+pub const LOCK_BLIMPO: &str = "blimpo"; // Blimpo got the final door open
+
+/// Every direction a passage can lead in, as (name, shortcuts).
+/// The relative words are kept for players used to them.
+
+pub const DIRECTIONS: [(&str, &[&str]); 12] = [
+    ("north", &["n", "forward"]),
+    ("northeast", &["ne"]),
+    ("east", &["e", "right"]),
+    ("southeast", &["se"]),
+    ("south", &["s"]),
+    ("southwest", &["sw"]),
+    ("west", &["w", "left"]),
+    ("northwest", &["nw"]),
+    ("up", &["u"]),
+    ("down", &["d"]),
+    ("in", &["inside"]),
+    ("out", &["outside"]),
+];
+
+/// Turns a direction, or a shortcut for one, into its full name.
+
+pub fn direction(word: &str) -> Option<&'static str> {
+    DIRECTIONS.iter()
+              .find(|d| d.0 == word || d.1.contains(&word))
+              .map(|d| d.0)
+}
+
+/// Every passage in the classic jail, as (from, direction, to, lock,
+/// message shown when going through), where an empty lock means the
+/// passage is always open.
+
+const CLASSIC_EXITS: [(usize, &str, usize, &str, &str); 32] = [
+    (0, "north", 1, LOCK_KEY, "You head through the door.\nFreedom! Kind of..."),
+    (1, "north", 2, "", "You sally forth!"),
+    (2, "north", 3, "", "You speedwalk out of the old man's cold stare."),
+    (6, "north", 7, LOCK_BLIMPO, "With the door now unlocked, \
+                                  you head through, filled with anxiety."),
+    (7, "north", OUTSIDE, "", ""),
+    (10, "north", 11, "", "You head into the open door."),
+    (12, "north", 0, "", "You head back into your cell."),
+    (3, "west", 4, "", "You head further into the corridor."),
+    (4, "west", 5, "", "You head further into the corridor."),
+    (5, "west", 6, "", "You head towards the end of the corridor."),
+    (8, "west", 1, "", "You hurriedly vacate the soiled cell."),
+    (9, "west", 3, "", "You wade through the furniture and out of the room."),
+    (10, "west", 9, "", "You head back into the sea of furniture."),
+    (0, "south", 12, LOCK_WALL, "You head through the opening and \
+                                 into the strange, small room."),
+    (1, "south", 0, "", "You nervously head back into your cell..."),
+    (2, "south", 1, "", "You speedwalk out of the old man's cold stare."),
+    (3, "south", 2, "", "You enter the room with the old man."),
+    (7, "south", 6, "", "You leave the laundry room and head back."),
+    (11, "south", 10, "", "You head out from the dank storage room."),
+    (1, "east", 8, LOCK_STENCH, "You ready yourself, take a deep breath, \
+                                 and head into the stench."),
+    (3, "east", 9, "", "You maneuver your way into the sea of furniture."),
+    (4, "east", 3, "", "You head back into the T-junction."),
+    (5, "east", 4, "", "You head into the eastern corridor."),
+    (6, "east", 5, "", "You head back into the dark corridor."),
+    (9, "east", 10, "", "You wade through the last of the furniture\n\
+                         and head into the makeshift break room."),
+    (9, "northeast", 11, "", "You squeeze through a gap in the furniture\n\
+                              and into a damp storage room."),
+    (11, "southwest", 9, "", "You squeeze back into the sea of furniture."),
+    (0, "in", 12, LOCK_WALL, "You head through the opening and \
+                              into the strange, small room."),
+    (12, "out", 0, "", "You head back into your cell."),
+    (8, "out", 1, "", "You hurriedly vacate the soiled cell."),
+    (11, "out", 10, "", "You head out from the dank storage room."),
+    (7, "out", OUTSIDE, "", ""),
+];
+
+/// The name of each room in the classic jail, by room number.
+
+const CLASSIC_NAMES: [&str; 13] = [
+    "Your Cell", "Cell Block", "Old Man's Corridor", "T-Junction",
+    "Dark Corridor", "Far Corridor", "Corridor's End", "Laundry Room",
+    "Smelly Cell", "Furniture Storage", "Break Room", "Storage Room",
+    "Secret Room",
+];
+
+/// Where each room in the classic jail sits on a grid, by room
+/// number, with north being up. Used to draw the map.
+
+const CLASSIC_COORDS: [(i32, i32); 13] = [
+    (0, 0), (0, 1), (0, 2), (0, 3), (-1, 3), (-2, 3), (-3, 3),
+    (-3, 4), (1, 1), (1, 3), (2, 3), (2, 4), (0, -1),
+];
+
+/// What the player is told when they run into each classic lock.
+
+fn classic_blocked(lock: &str) -> &'static str {
+    match lock {
+        LOCK_KEY    => "The door is locked tight.",
+        LOCK_WALL   => "Somethings clearly off about the wall,\n\
+                        but you can't just walk through it.",
+        LOCK_STENCH => "The stench radiating from the cell is unbearable.\n\
+                        Nothing inside looks interesting enough \
+                        to justify going in.",
+        _           => "There's an entire human-being in your way, \
+                        unfortunately.",
+    }
+}
+
+/// Represents a passage leading out of a room in some direction.
+/// A passage with a lock can only be used once it's been opened.
+
+#[derive(Clone)]
+pub struct Exit {
+    pub from: usize,
+    pub dir: String,
+    pub to: usize,
+    pub lock: String,
+    pub message: String,
+    pub blocked: String,
+}
+
+/// Represents the layout the player is exploring: every passage
+/// between rooms, plus anything a generated map needs to remember.
+/// Rooms with an entry in descs are described by that text,
+/// instead of by the room data files.
+/// Every room has a short name, for brief descriptions,
+/// and a spot on a grid, for the map.
+
+#[derive(Clone)]
+pub struct Map {
+    pub start: usize,
+    pub exits: Vec<Exit>,
+    pub descs: Vec<(usize, String)>,
+    pub names: Vec<(usize, String)>,
+    pub coords: Vec<(usize, (i32, i32))>,
+    pub keys: Vec<(String, usize)>,
+}
+
+/// The implementation of the Map struct.
+
+impl Map {
+
+    /// Create the map of the classic, hand-made jail.
+
+    pub fn classic() -> Map {
+        Map {
+            start: CELL,
+            exits: CLASSIC_EXITS.iter().map(|e| Exit {
+                from: e.0,
+                dir: e.1.to_string(),
+                to: e.2,
+                lock: e.3.to_string(),
+                message: e.4.to_string(),
+                blocked: classic_blocked(e.3).to_string(),
+            }).collect(),
+            descs: Vec::new(),
+            names: CLASSIC_NAMES.iter()
+                                .enumerate()
+                                .map(|(i, n)| (i, n.to_string()))
+                                .collect(),
+            coords: CLASSIC_COORDS.iter().cloned().enumerate().collect(),
+            keys: Vec::new(),
+        }
+    }
+
+    /// Finds the passage leading out of a room in the given direction.
+
+    pub fn exit(&self, from: usize, dir: &str) -> Option<&Exit> {
+        self.exits.iter().find(|e| e.from == from && e.dir == dir)
+    }
+
+    /// Finds the direction leading from one room straight into another,
+    /// if the two rooms are connected at all.
+
+    pub fn direction_to(&self, from: usize, to: usize) -> Option<&str> {
+        self.exits.iter()
+                  .find(|e| e.from == from && e.to == to)
+                  .map(|e| e.dir.as_str())
+    }
+
+    /// The generated description of a room, if it has one.
+
+    pub fn desc(&self, room: usize) -> Option<&str> {
+        self.descs.iter()
+                  .find(|d| d.0 == room)
+                  .map(|d| d.1.as_str())
+    }
+
+    /// The short name of a room.
+
+    pub fn name(&self, room: usize) -> &str {
+        self.names.iter()
+                  .find(|n| n.0 == room)
+                  .map(|n| n.1.as_str())
+                  .unwrap_or("Somewhere")
+    }
+
+    /// Where a room sits on the map's grid, if it's on the map at all.
+
+    pub fn coords(&self, room: usize) -> Option<(i32, i32)> {
+        self.coords.iter()
+                   .find(|c| c.0 == room)
+                   .map(|c| c.1)
+    }
+
+    /// Whether a room is part of this map, as in
+    /// there's a way out of it.
+
+    pub fn has_room(&self, room: usize) -> bool {
+        self.exits.iter().any(|e| e.from == room)
+    }
+
+    /// Every passage leading out of a room.
+
+    pub fn exits_from(&self, room: usize) -> Vec<&Exit> {
+        self.exits.iter().filter(|e| e.from == room).collect()
+    }
+
+    /// Finds the shortest way from one room to another, using only
+    /// the passages can_use allows, as a list of (direction, room
+    /// it leads to) steps. The way from a room to itself is empty.
+
+    pub fn path(&self, from: usize, to: usize,
+                can_use: &dyn Fn(&Exit) -> bool)
+                -> Option<Vec<(String, usize)>> {
+        // Each room found so far, with the step that first reached it.
+        let mut found: Vec<(usize, Option<(usize, &str)>)> = vec![(from, None)];
+        let mut next = 0;
+        while next < found.len() && found[next].0 != to {
+            let here = found[next].0;
+            for exit in self.exits.iter().filter(|e| e.from == here) {
+                if can_use(exit) && !found.iter().any(|f| f.0 == exit.to) {
+                    found.push((exit.to, Some((here, exit.dir.as_str()))));
+                }
+            }
+            next += 1;
+        }
+        let mut room = found.iter().position(|f| f.0 == to)?;
+        let mut steps = Vec::new();
+        while let Some((prev, dir)) = found[room].1 {
+            steps.push((dir.to_string(), found[room].0));
+            room = found.iter().position(|f| f.0 == prev).unwrap();
+        }
+        steps.reverse();
+        Some(steps)
+    }
+
+    /// Finds every room the player could walk to from the start,
+    /// where is_open decides which locks the player can get through.
+
+    pub fn reachable(&self, is_open: &dyn Fn(&str) -> bool) -> Vec<usize> {
+        let mut found = vec![self.start];
+        let mut next = 0;
+        while next < found.len() {
+            let from = found[next];
+            for exit in self.exits.iter().filter(|e| e.from == from) {
+                if !found.contains(&exit.to)
+                   && (exit.lock.is_empty() || is_open(&exit.lock)) {
+                    found.push(exit.to);
+                }
+            }
+            next += 1;
+        }
+        found
+    }
+}
+
+/// Whether a passage with the given lock should be kept
+/// from the player until they've found a way through it.
+
+pub fn is_secret(lock: &str) -> bool {
+    lock == LOCK_WALL
+}
+
+/// Moves the player through the passage leading out of their room
+/// in the given direction, if there is one and it isn't locked.
+/// has_opened decides which locks the player can get through.
+///
+/// Returns the room the player ends up in.
+
+pub fn go(map: &Map, room_index: usize, dir: &str,
+          has_opened: &dyn Fn(&str) -> bool) -> usize {
+    match map.exit(room_index, dir) {
+        Some(exit) if exit.lock.is_empty() || has_opened(&exit.lock) => {
+            if !exit.message.is_empty() {
+                println!("\n\n{}", exit.message);
+            }
+            exit.to
+        }
+        Some(exit) => {
+            println!("\n\n{}", exit.blocked);
+            room_index
+        }
+        None       => {
+            println!("\n\nYou can't go {} from here.", dir);
+            room_index
+        }
+    }
+}
 
 // Room text data file path constants
 const R0_TEXT_0: &str  = "../data/room/r0text0.txt";
@@ -15,11 +318,8 @@ const R1_TEXT_0: &str  = "../data/room/r1text0.txt";
 const R2_TEXT_0: &str  = "../data/room/r2text0.txt";
 const R3_TEXT_0: &str  = "../data/room/r3text0.txt";
 const R4_TEXT_0: &str  = "../data/room/r4text0.txt";
-const R4_TEXT_1: &str  = "../data/room/r4text1.txt";
 const R5_TEXT_0: &str  = "../data/room/r5text0.txt";
-const R5_TEXT_1: &str  = "../data/room/r5text1.txt";
 const R6_TEXT_0: &str  = "../data/room/r6text0.txt";
-const R6_TEXT_1: &str  = "../data/room/r6text1.txt";
 const R7_TEXT_0: &str  = "../data/room/r7text0.txt";
 const R8_TEXT_0: &str  = "../data/room/r8text0.txt";
 const R8_TEXT_1: &str  = "../data/room/r8text1.txt";
@@ -30,242 +330,86 @@ const R11_TEXT_1: &str = "../data/room/r11text1.txt";
 const R12_TEXT_0: &str = "../data/room/r12text0.txt";
 const R12_TEXT_1: &str = "../data/room/r12text1.txt";
 
-/// Based on usize room index, prints a specific room's description.
-/// Some rooms have alternate text displayed based on game state.
+/// Based on usize room index, finds the file holding a specific
+/// room's description. Some rooms have alternate text based on
+/// game state, and events can swap out a room's text entirely
+/// (see events.rs).
 ///
 /// # Panics
 ///
 /// Will panic if given a room that can't be matched,
 /// as all rooms are expected to be accounted for.
 
-pub fn get_desc(room_index: usize, has_key: bool,
-                found_room: bool, has_broom: bool,
-                has_nail: bool, blimpo_left: bool) {
+pub fn desc_path(room_index: usize, has_key: bool,
+                 found_room: bool, has_broom: bool,
+                 has_nail: bool) -> &'static str {
     match room_index {
         0  => {
-                  if found_room == false { print_from_file(R0_TEXT_0) }
-                  else { print_from_file(R0_TEXT_1) }
-              },
-        1  => print_from_file(R1_TEXT_0),
-        2  => print_from_file(R2_TEXT_0),
-        3  => print_from_file(R3_TEXT_0),
-        4  => {
-                  if blimpo_left == false { print_from_file(R4_TEXT_0) }
-                  else { print_from_file(R4_TEXT_1) }
+                  if !found_room { R0_TEXT_0 }
+                  else { R0_TEXT_1 }
               },
-        5  => {
-                  if blimpo_left == false { print_from_file(R5_TEXT_0) }
-                  else { print_from_file(R5_TEXT_1) }
-              },
-        6  => {
-                  if blimpo_left == false { print_from_file(R6_TEXT_0) }
-                  else { print_from_file(R6_TEXT_1) }
-              },
-        7  => print_from_file(R7_TEXT_0),
+        1  => R1_TEXT_0,
+        2  => R2_TEXT_0,
+        3  => R3_TEXT_0,
+        4  => R4_TEXT_0,
+        5  => R5_TEXT_0,
+        6  => R6_TEXT_0,
+        7  => R7_TEXT_0,
         8  => {
-                  if has_nail == false { print_from_file(R8_TEXT_0) }
-                  else { print_from_file(R8_TEXT_1) }
+                  if !has_nail { R8_TEXT_0 }
+                  else { R8_TEXT_1 }
               },
-        9  => print_from_file(R9_TEXT_0),
-        10 => print_from_file(R10_TEXT_0),
+        9  => R9_TEXT_0,
+        10 => R10_TEXT_0,
         11 => {
-                  if has_broom == false { print_from_file(R11_TEXT_0) }
-                  else { print_from_file(R11_TEXT_1) }
+                  if !has_broom { R11_TEXT_0 }
+                  else { R11_TEXT_1 }
               },
         12 => {
-                  if has_key == false { print_from_file(R12_TEXT_0) }
-                  else { print_from_file(R12_TEXT_1) }
+                  if !has_key { R12_TEXT_0 }
+                  else { R12_TEXT_1 }
               },
         _  => panic!() // We should be guaranteed a valid room number.
     }
 }
 
-/// Move player to room immediately North of the
-/// current room, or not, if there is no such room.
-
-pub fn go_north(room_index: usize, has_key: bool,
-                final_room_unlocked: bool) -> usize {
-    match room_index {
-        0    => {
-                     if has_key {
-                         println!("\n\nYou head through the door.\n\
-                                  Freedom! Kind of...");
-                         1
-                     } else {
-                         println!("\n\nThe door is locked tight.");
-                         0
-                     }
-                 }
-        1     => {
-                     println!("\n\nYou sally forth!");
-                     2
-                 }
-        2     => {
-                     println!("\n\nYou speedwalk out of \
-                               the old man's cold stare.");
-                     3
-                 }
-        6     => {
-                     if final_room_unlocked {
-                         println!("\n\nWith the door now unlocked, \
-                                   you head through, filled with anxiety.");
-                         7
-                     } else {
-                         println!("\n\nThere's an entire human-\
-                                   being in your way, unfortunately.");
-                         6
-                     }
-                 }
-        10    => {
-                     println!("\n\nYou head into the open door.");
-                     11
-                 }
-        12    => {
-                     println!("\n\nYou head back into your cell.");
-                     0
-                 }
-        other => {
-                     println!("\n\nYou can't go north from here.");
-                     other
-                 }
-    }
+#[test]
+fn reachable_test() {
+    let map = Map::classic();
+    // Without the key, only the cell and the secret room.
+    let mut locked_in = map.reachable(&|lock| lock == LOCK_WALL);
+    locked_in.sort();
+    assert_eq!(vec![CELL, SECRET_ROOM], locked_in);
+    // Everything but the smelly cell, the final room, and outside.
+    let out = map.reachable(&|lock| lock != LOCK_STENCH
+                                    && lock != LOCK_BLIMPO);
+    assert_eq!(11, out.len());
+    assert!(map.reachable(&|_| true).contains(&OUTSIDE));
 }
 
-/// Move player to room immediately West of the
-/// current room, or not, if there is no such room.
-
-pub fn go_west(room_index: usize) -> usize {
-    match room_index {
-        3     => {
-                     println!("\n\nYou head further into the corridor.");
-                     4
-                 }
-        4     => {
-                     println!("\n\nYou head further into the corridor.");
-                     5
-                 }
-        5     => {
-                     println!("\n\nYou head towards the end of the corridor.");
-                     6
-                 }
-        8     => {
-                     println!("\n\nYou hurriedly vacate the soiled cell.");
-                     1
-                 }
-        9     => {
-                     println!("\n\nYou wade through the furniture and out of the room.");
-                     3
-                 }
-        10    => {
-                     println!("\n\nYou head back into the sea of furniture.");
-                     9
-                 }
-        other => {
-                     println!("\n\nYou can't go west from here.");
-                     other
-                 }
-    }
+#[test]
+fn direction_test() {
+    assert_eq!(Some("northeast"), direction("ne"));
+    assert_eq!(Some("up"), direction("up"));
+    assert_eq!(Some("west"), direction("left"));
+    assert_eq!(None, direction("sideways"));
 }
 
-/// Move player to room immediately South of the
-/// current room, or not, if there is no such room.
-
-pub fn go_south(room_index: usize, found_room: bool) -> usize {
-    match room_index {
-        0     => {
-                     if found_room {
-                         println!("\n\nYou head through the opening and \
-                                   into the strange, small room.");
-                         12
-                     } else {
-                         println!("\n\nSomethings clearly off about the wall,\n\
-                                   but you can't just walk through it.");
-                         0
-                     }
-                 }
-        1     => {
-                     println!("\n\nYou nervously head back into your cell...");
-                     0
-                 }
-        2     => {
-                     println!("\n\nYou speedwalk out of the old man's cold stare.");
-                     1
-                 }
-        3     => {
-                     println!("\n\nYou enter the room with the old man."); 
-                     2
-                 }
-        7     => {
-                     println!("\n\nYou leave the laundry room and head back.");
-                     6
-                 }
-        11    => {
-                     println!("\n\nYou head out from the dank storage room.");
-                     10
-                 }
-        other => {
-                     println!("\n\nYou can't go south from here.");
-                     other
-                 }
-    }
+#[test]
+fn path_test() {
+    let map = Map::classic();
+    let steps = map.path(11, 2, &|_| true).unwrap();
+    let dirs: Vec<&str> = steps.iter().map(|s| s.0.as_str()).collect();
+    assert_eq!(vec!["southwest", "west", "south"], dirs);
+    assert_eq!(2, steps.last().unwrap().1);
+    assert!(map.path(1, 8, &|e| e.lock.is_empty()).is_none());
+    assert_eq!(Some(Vec::new()), map.path(3, 3, &|_| true));
 }
 
-/// Move player to room immediately East of the
-/// current room, or not, if there is no such room.
-
-pub fn go_east(room_index: usize, met_blimpo: bool) -> usize {
-    match room_index {
-        1     => {
-                     if met_blimpo {
-                         println!("\n\nYou ready yourself, \
-                                   take a deep breath, and \
-                                   head into the stench.");
-                         8
-                     } else {
-                         println!("\n\nThe stench radiating from the cell \
-                                   is unbearable.\nNothing inside looks \
-                                   interesting enough to justify going in.");
-                         1
-                     }
-                 }
-        3     => {
-                     println!("\n\nYou maneuver your way into the sea of furniture.");
-                     9
-                 }
-        4     => {
-                     println!("\n\nYou head back into the T-junction.");
-                     3
-                 }
-        5     => {
-                     println!("\n\nYou head into the eastern corridor.");
-                     4
-                 }
-        6     => {
-                     println!("\n\nYou head back into the dark corridor.");
-                     5
-                 }
-        9     => {
-                     println!("\n\nYou wade through the last of the furniture\n\
-                               and head into the makeshift break room.");
-                     10
-                 }
-        other => {
-                     println!("\n\nYou can't go east from here.");
-                     other
-                 }
-    }
+#[test]
+fn go_test() {
+    let map = Map::classic();
+    assert_eq!(1, go(&map, CELL, "north", &|_| true));
+    assert_eq!(CELL, go(&map, CELL, "north", &|_| false));
+    assert_eq!(CELL, go(&map, CELL, "west", &|_| true));
 }
-
-
-
-
-
-
-
-
-
-
-
-
-
-
@@ -0,0 +1,139 @@
+use crate::helpers;
+use crate::items;
+use crate::state::{State, FLAGS};
+use crate::status;
+
+/// Represents something the player can look at, as declared
+/// in the scenery data file. It can be in certain rooms,
+/// anywhere at all, or carried by the player.
+
+pub struct Scenery {
+    pub place: String,
+    pub names: Vec<String>,
+    pub desc: String,
+    pub alternates: Vec<(String, String)>,
+}
+
+/// Reads all the scenery out of the blocks of a scenery file,
+/// one entry to a block. Each starts with where it
+/// is: room numbers separated by commas, '*' for anywhere, or
+/// 'has <item>' for something the player carries. Next come the
+/// names it goes by, then its description. Any further lines are
+/// 'flag text' alternates, used once that flag, status effect,
+/// or script flag is set; the last one that applies wins.
+
+pub fn parse(blocks: &[Vec<String>]) -> Vec<Scenery> {
+    let mut scenery = Vec::new();
+    for block in blocks {
+        let mut lines = block.iter();
+        let (place, names, desc) = match (lines.next(), lines.next(),
+                                          lines.next()) {
+            (Some(p), Some(n), Some(d)) => (p, n, d),
+            _                           => continue,
+        };
+        let alternates = lines.filter_map(|l| {
+            let mut parts = l.splitn(2, ' ');
+            match (parts.next(), parts.next()) {
+                (Some(flag), Some(text)) => Some((flag.to_string(),
+                                                  unescape(text))),
+                _                        => None,
+            }
+        }).collect();
+        scenery.push(Scenery {
+            place: place.to_string(),
+            names: names.split_whitespace().map(|n| n.to_string()).collect(),
+            desc: unescape(desc),
+            alternates,
+        });
+    }
+    scenery
+}
+
+/// Turns the '\n's written in data files into real line breaks.
+
+fn unescape(text: &str) -> String {
+    text.replace("\\n", "\n")
+}
+
+/// Loads the scenery from the file at the given path.
+
+pub fn load(path: &str) -> Vec<Scenery> {
+    parse(&helpers::read_blocks(path))
+}
+
+/// Whether the given flag, status effect, or script flag is set.
+
+fn is_set(gstate: &State, name: &str) -> bool {
+    match FLAGS.iter().position(|&f| f == name) {
+        Some(i) => gstate.flag(i as u8 + 1),
+        None    => status::has(gstate, name)
+                   || gstate.script_flags.iter().any(|f| f == name),
+    }
+}
+
+/// Finds what the player means by the given noun, looking first at
+/// the scenery in their room, then at what they're carrying, and
+/// then at anything that can be looked at anywhere.
+
+pub fn resolve<'a>(scenery: &'a [Scenery], gstate: &State,
+                   noun: &str) -> Option<&'a Scenery> {
+    let named = |s: &&Scenery| s.names.iter().any(|n| n == noun);
+    let in_room = |s: &&Scenery| s.place.split(',')
+                                        .any(|r| r == gstate.curr_room
+                                                          .to_string());
+    let carried = |s: &&Scenery| match s.place.strip_prefix("has ") {
+        Some(item) => items::is_carried(gstate, item),
+        None       => false,
+    };
+    scenery.iter().filter(named).find(in_room)
+           .or_else(|| scenery.iter().filter(named).find(carried))
+           .or_else(|| scenery.iter().filter(named).find(|s| s.place == "*"))
+}
+
+/// Every name the player could use right now to
+/// refer to a piece of scenery with resolve().
+
+pub fn names_in_scope<'a>(scenery: &'a [Scenery], gstate: &State) -> Vec<&'a str> {
+    let mut names: Vec<&str> = scenery.iter()
+                                      .flat_map(|s| s.names.iter())
+                                      .map(|n| n.as_str())
+                                      .filter(|n| resolve(scenery, gstate, n)
+                                                  .is_some())
+                                      .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+/// The description of a piece of scenery as things stand.
+
+pub fn describe<'a>(scenery: &'a Scenery, gstate: &State) -> &'a str {
+    scenery.alternates.iter()
+                      .rev()
+                      .find(|a| is_set(gstate, &a.0))
+                      .map(|a| a.1.as_str())
+                      .unwrap_or(&scenery.desc)
+}
+
+#[test]
+fn resolve_test() {
+    let scenery = parse(&helpers::blocks("# Things to look at.\n\
+                                          0,12\nwall walls\nA wall.\n\
+                                          examined_wall A hole.\n\n\
+                                          has nail\nnail\nA nail.\n\n\
+                                          *\nwall\nJust a wall.\n"));
+    let mut gstate = State::empty();
+    assert_eq!("A wall.", describe(resolve(&scenery, &gstate, "walls")
+                                   .unwrap(), &gstate));
+    gstate.examined_wall = true;
+    assert_eq!("A hole.", describe(resolve(&scenery, &gstate, "wall")
+                                   .unwrap(), &gstate));
+    gstate.curr_room = 3;
+    assert_eq!("Just a wall.", resolve(&scenery, &gstate, "wall")
+                               .unwrap().desc);
+    // The nail can only be looked at while it's being carried.
+    assert!(resolve(&scenery, &gstate, "nail").is_none());
+    gstate.took_nail = true;
+    assert!(resolve(&scenery, &gstate, "nail").is_some());
+    assert_eq!(vec!["nail", "wall"], names_in_scope(&scenery, &gstate));
+}
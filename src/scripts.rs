@@ -0,0 +1,192 @@
+use crate::helpers;
+use crate::items;
+use crate::state::{State, FLAGS};
+use rhai::{Dynamic, Engine, EvalAltResult};
+use std::cell::RefCell;
+use std::fs;
+use std::rc::Rc;
+
+/// How much work a script may do before it's stopped, so
+/// a script stuck in a loop can't freeze the game.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// Represents a script attached to a verb, as declared in the
+/// scripts data file. A script runs when the player uses its verb
+/// on its object, in its room, where '*' matches anything.
+
+pub struct Hook {
+    pub verb: String,
+    pub object: String,
+    pub room: String,
+    pub path: String,
+}
+
+/// Reads every hook out of the text of a scripts file, one per line,
+/// as 'verb object room path'. Lines starting with '#' are comments.
+
+pub fn parse(data: &str) -> Vec<Hook> {
+    data.lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| {
+            let words: Vec<&str> = l.split_whitespace().collect();
+            match words.as_slice() {
+                [verb, object, room, path] => Some(Hook {
+                    verb: verb.to_string(),
+                    object: object.to_string(),
+                    room: room.to_string(),
+                    path: path.to_string(),
+                }),
+                _                          => None,
+            }
+        })
+        .collect()
+}
+
+/// Loads the hooks from the file at the given path.
+
+pub fn load(path: &str) -> Vec<Hook> {
+    parse(&helpers::read_data(path))
+}
+
+/// Finds the script attached to the given verb and object
+/// in the given room, if there is one.
+
+pub fn find<'a>(hooks: &'a [Hook], verb: &str, object: &str,
+                room: usize) -> Option<&'a str> {
+    hooks.iter()
+         .find(|h| h.verb == verb
+                   && (h.object == "*" || h.object == object)
+                   && (h.room == "*" || h.room == room.to_string()))
+         .map(|h| h.path.as_str())
+}
+
+/// Runs the script in the file at the given path.
+/// A script that can't be read is reported and skipped.
+///
+/// Returns the same as run().
+
+pub fn run_file(path: &str, gstate: State) -> (State, bool) {
+    match fs::read_to_string(path) {
+        Ok(source) => run(&source, gstate),
+        Err(_)     => {
+            println!("\n\nCan't read script {}.", path);
+            (gstate, false)
+        }
+    }
+}
+
+/// Runs a script against the game state. Scripts can call:
+///
+/// - print(text): show the player some text
+/// - flag(name) / set_flag(name, value): read and write flags,
+///   either the game's own or any other name a script makes up
+/// - room() / move_to(room): get and change the player's room,
+///   which has to be a room on the map
+/// - has(item): whether the player is carrying an item or key
+/// - turns() / tick(): get the turn count and take a turn
+///
+/// Returns the game state the script left behind, and whether
+/// the script handled the command. A script handles its command
+/// unless it ends by returning false, or fails with an error.
+
+pub fn run(source: &str, gstate: State) -> (State, bool) {
+    let shared = Rc::new(RefCell::new(gstate));
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.on_print(|text| println!("\n\n{}", text));
+    let s = shared.clone();
+    engine.register_fn("flag", move |name: &str| {
+        let gstate = s.borrow();
+        match FLAGS.iter().position(|&f| f == name) {
+            Some(i) => gstate.flag(i as u8 + 1),
+            None    => gstate.script_flags.iter().any(|f| f == name),
+        }
+    });
+    let s = shared.clone();
+    engine.register_fn("set_flag", move |name: &str, value: bool| {
+        let mut gstate = s.borrow_mut();
+        match FLAGS.iter().position(|&f| f == name) {
+            Some(i) => *gstate = gstate.update((i as u8 + 1, value)),
+            None    => {
+                gstate.script_flags.retain(|f| f != name);
+                if value {
+                    gstate.script_flags.push(name.to_string());
+                }
+            },
+        }
+    });
+    let s = shared.clone();
+    engine.register_fn("room", move || s.borrow().curr_room as i64);
+    let s = shared.clone();
+    engine.register_fn("move_to", move |room: i64| -> Result<(), Box<EvalAltResult>> {
+        let mut gstate = s.borrow_mut();
+        if room < 0 || !gstate.map.has_room(room as usize) {
+            return Err(format!("There's no room {}", room).into())
+        }
+        if gstate.curr_room != room as usize {
+            // There's no retracing a step the player didn't take.
+            gstate.curr_room = room as usize;
+            gstate.trail.clear();
+        }
+        Ok(())
+    });
+    let s = shared.clone();
+    engine.register_fn("has", move |item: &str| {
+        items::is_carried(&s.borrow(), item)
+    });
+    let s = shared.clone();
+    engine.register_fn("turns", move || s.borrow().turns as i64);
+    let s = shared.clone();
+    engine.register_fn("tick", move || {
+        let mut gstate = s.borrow_mut();
+        gstate.turns += 1;
+    });
+    let handled = match engine.eval::<Dynamic>(source) {
+        Ok(result) => result.as_bool().unwrap_or(true),
+        Err(err)   => {
+            println!("\n\nScript error: {}", err);
+            false
+        }
+    };
+    let gstate = shared.borrow().clone();
+    (gstate, handled)
+}
+
+#[test]
+fn find_test() {
+    let hooks = parse("# A comment\nlook table 10 a.rhai\n\
+                       talk * * b.rhai\nbroken line\n");
+    assert_eq!(2, hooks.len());
+    assert_eq!(Some("a.rhai"), find(&hooks, "look", "table", 10));
+    assert_eq!(None, find(&hooks, "look", "table", 3));
+    assert_eq!(Some("b.rhai"), find(&hooks, "talk", "", 0));
+}
+
+#[test]
+fn run_test() {
+    let mut start = State::empty();
+    start.trail = vec![1];
+    let (gstate, handled) = run("if !flag(\"examined_wall\") {\n\
+                                     set_flag(\"examined_wall\", true);\n\
+                                     set_flag(\"read_note\", true);\n\
+                                     move_to(12);\n\
+                                     tick();\n\
+                                 }", start);
+    assert!(handled);
+    assert!(gstate.examined_wall);
+    assert_eq!(10, gstate.score);
+    assert_eq!(vec!["read_note".to_string()], gstate.script_flags);
+    assert_eq!(12, gstate.curr_room);
+    assert!(gstate.trail.is_empty());
+    assert_eq!(1, gstate.turns);
+    // Returning false lets the built-in command go ahead.
+    let (gstate, handled) = run("has(\"key\")", gstate);
+    assert!(!handled);
+    // Scripts can't send the player off the map, or run forever.
+    let (gstate, handled) = run("move_to(-1);", gstate);
+    assert!(!handled);
+    assert_eq!(12, gstate.curr_room);
+    let (_, handled) = run("loop {}", gstate);
+    assert!(!handled);
+}
@@ -1,4 +1,9 @@
 
+use crate::status::{self, Effect};
+use crate::rng::Rng;
+use crate::room::{self, Map};
+use crate::wing;
+
 /// Represents the gamestate as a collection
 /// of information about the current game.
 ///
@@ -6,6 +11,7 @@
 /// denoting which rooms are accessible,
 /// which items have been picked up, etc.
 
+#[derive(Clone)]
 pub struct State {
     pub curr_room: usize,
     pub examined_wall: bool,
@@ -16,6 +22,82 @@ pub struct State {
     pub met_blimpo: bool,
     pub final_room_unlocked: bool,
     pub wearing_clothes: bool,
+    pub turns: u32,
+    pub ng_plus: u32,
+    pub score: u32,
+    pub health: u32,
+    pub effects: Vec<Effect>,
+    pub rng: Rng,
+    pub mode: String,
+    pub key_room: usize,
+    pub broom_room: usize,
+    pub nail_room: usize,
+    pub map_seed: u64,
+    pub keys: Vec<String>,
+    pub map: Map,
+    pub fired: Vec<String>,
+    pub room_texts: Vec<(usize, String)>,
+    pub script_flags: Vec<String>,
+    pub visited: Vec<usize>,
+    pub verbosity: String,
+    pub status_line: bool,
+    pub trail: Vec<usize>,
+    pub hints: Vec<String>,
+}
+
+/// The names of the update() flags, in the same order as
+/// their numbering, so data files can refer to them by name.
+
+pub const FLAGS: [&str; 8] = ["examined_wall", "took_key", "took_broom",
+                              "helped_carl", "took_nail", "met_blimpo",
+                              "final_room_unlocked", "wearing_clothes"];
+
+/// The most health the player can have.
+pub const MAX_HEALTH: u32 = 20;
+
+// The in-game clock starts at 10:00 PM, and every turn takes 5 minutes.
+const CLOCK_START_MINUTES: u32 = 22 * 60;
+const MINUTES_PER_TURN: u32 = 5;
+
+/// Points awarded for leaving through the final door.
+/// Only ever added on the ending screen.
+pub const ESCAPE_POINTS: u32 = 25;
+
+/// The most points a run can be worth.
+pub const MAX_SCORE: u32 = milestone_total() + ESCAPE_POINTS;
+
+/// Points taken off the score for each hint the player asks for.
+pub const HINT_COST: u32 = 2;
+
+/// The most steps the player can retrace with 'back'.
+pub const MAX_TRAIL: usize = 50;
+
+/// Points awarded the first time each update() flag is set,
+/// for the milestones that are worth something.
+
+const fn milestone_points(flag: u8) -> u32 {
+    match flag {
+        1 => 10, // Found the secret room
+        2 => 10, // Got the key
+        3 => 5,  // Found Carl's broom
+        4 => 20, // Helped Carl
+        5 => 5,  // Found Blimpo's nail
+        7 => 15, // Got the final door unlocked
+        8 => 10, // Put on the uniform
+        _ => 0,
+    }
+}
+
+/// Every milestone's points added up.
+
+const fn milestone_total() -> u32 {
+    let mut total = 0;
+    let mut flag = 1;
+    while flag as usize <= FLAGS.len() {
+        total += milestone_points(flag);
+        flag += 1;
+    }
+    total
 }
 
 /// The implementation of the State struct.
@@ -35,11 +117,33 @@ impl State {
             met_blimpo: false,
             final_room_unlocked: false,
             wearing_clothes: false,
+            turns: 0,
+            ng_plus: 0,
+            score: 0,
+            health: MAX_HEALTH,
+            effects: Vec::new(),
+            rng: Rng::new(0),
+            mode: "classic".to_string(),
+            key_room: room::SECRET_ROOM,
+            broom_room: room::BROOM_ROOM,
+            nail_room: room::SMELLY_CELL,
+            map_seed: 0,
+            keys: Vec::new(),
+            map: Map::classic(),
+            fired: Vec::new(),
+            room_texts: Vec::new(),
+            script_flags: Vec::new(),
+            visited: Vec::new(),
+            verbosity: "brief".to_string(),
+            status_line: false,
+            trail: Vec::new(),
+            hints: Vec::new(),
         }
     }
 
     /// Create a new State based on the passed in arguments.
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new(curr_room: usize, examined_wall: bool, took_key: bool,
                took_broom: bool, helped_carl: bool,
                took_nail: bool, met_blimpo: bool,
@@ -54,15 +158,37 @@ impl State {
             met_blimpo,
             final_room_unlocked,
             wearing_clothes,
+            turns: 0,
+            ng_plus: 0,
+            score: 0,
+            health: MAX_HEALTH,
+            effects: Vec::new(),
+            rng: Rng::new(0),
+            mode: "classic".to_string(),
+            key_room: room::SECRET_ROOM,
+            broom_room: room::BROOM_ROOM,
+            nail_room: room::SMELLY_CELL,
+            map_seed: 0,
+            keys: Vec::new(),
+            map: Map::classic(),
+            fired: Vec::new(),
+            room_texts: Vec::new(),
+            script_flags: Vec::new(),
+            visited: Vec::new(),
+            verbosity: "brief".to_string(),
+            status_line: false,
+            trail: Vec::new(),
+            hints: Vec::new(),
         }
     }
 
     /// Takes a path to the save file and returns the
     /// game state that can be built from the info in that file.
+    /// A generated map is rebuilt from its seed.
 
     pub fn deserialize(save_data: &str) -> State {
         let mut entries = save_data.lines();
-        State {
+        let gstate = State {
             curr_room: entries.next().unwrap().parse().unwrap(),
             examined_wall: entries.next().unwrap().parse().unwrap(),
             took_key: entries.next().unwrap().parse().unwrap(),
@@ -72,13 +198,72 @@ impl State {
             met_blimpo: entries.next().unwrap().parse().unwrap(),
             final_room_unlocked: entries.next().unwrap().parse().unwrap(),
             wearing_clothes: entries.next().unwrap().parse().unwrap(),
+            turns: entries.next().unwrap().parse().unwrap(),
+            ng_plus: entries.next().unwrap().parse().unwrap(),
+            score: entries.next().unwrap().parse().unwrap(),
+            health: entries.next().unwrap().parse().unwrap(),
+            effects: status::deserialize(entries.next().unwrap()),
+            rng: Rng::resume(entries.next().unwrap().parse().unwrap(),
+                             entries.next().unwrap().parse().unwrap()),
+            mode: entries.next().unwrap().to_string(),
+            key_room: entries.next().unwrap().parse().unwrap(),
+            broom_room: entries.next().unwrap().parse().unwrap(),
+            nail_room: entries.next().unwrap().parse().unwrap(),
+            map_seed: entries.next().unwrap().parse().unwrap(),
+            keys: entries.next().unwrap()
+                         .split(',')
+                         .filter(|k| !k.is_empty())
+                         .map(|k| k.to_string())
+                         .collect(),
+            map: Map::classic(),
+            fired: entries.next().unwrap()
+                          .split(',')
+                          .filter(|e| !e.is_empty())
+                          .map(|e| e.to_string())
+                          .collect(),
+            room_texts: entries.next().unwrap()
+                               .split(',')
+                               .filter(|t| !t.is_empty())
+                               .map(|t| {
+                                   let mut parts = t.splitn(2, '=');
+                                   (parts.next().unwrap().parse().unwrap(),
+                                    parts.next().unwrap().to_string())
+                               })
+                               .collect(),
+            script_flags: entries.next().unwrap()
+                                 .split(',')
+                                 .filter(|f| !f.is_empty())
+                                 .map(|f| f.to_string())
+                                 .collect(),
+            visited: entries.next().unwrap()
+                            .split(',')
+                            .filter(|r| !r.is_empty())
+                            .map(|r| r.parse().unwrap())
+                            .collect(),
+            verbosity: entries.next().unwrap().to_string(),
+            status_line: entries.next().unwrap().parse().unwrap(),
+            trail: entries.next().unwrap()
+                          .split(',')
+                          .filter(|r| !r.is_empty())
+                          .map(|r| r.parse().unwrap())
+                          .collect(),
+            hints: entries.next().unwrap()
+                          .split(',')
+                          .filter(|h| !h.is_empty())
+                          .map(|h| h.to_string())
+                          .collect(),
+        };
+        if gstate.mode == "wing" {
+            State { map: wing::generate(gstate.map_seed), ..gstate }
+        } else {
+            gstate
         }
     }
 
     /// Converts a State object into a String representation for saving.
 
     pub fn serialize(&self) -> String {
-        let s = format!("{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n~"
+        let s = format!("{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n~"
                         , &self.curr_room.to_string()
                         , &self.examined_wall.to_string()
                         , &self.took_key.to_string()
@@ -88,139 +273,239 @@ impl State {
                         , &self.met_blimpo.to_string()
                         , &self.final_room_unlocked.to_string()
                         , &self.wearing_clothes.to_string()
+                        , &self.turns.to_string()
+                        , &self.ng_plus.to_string()
+                        , &self.score.to_string()
+                        , &self.health.to_string()
+                        , status::serialize(&self.effects)
+                        , &self.rng.seed.to_string()
+                        , &self.rng.draws.to_string()
+                        , &self.mode
+                        , &self.key_room.to_string()
+                        , &self.broom_room.to_string()
+                        , &self.nail_room.to_string()
+                        , &self.map_seed.to_string()
+                        , &self.keys.join(",")
+                        , &self.fired.join(",")
+                        , &self.room_texts.iter()
+                                          .map(|t| format!("{}={}", t.0, t.1))
+                                          .collect::<Vec<String>>()
+                                          .join(",")
+                        , &self.script_flags.join(",")
+                        , &self.visited.iter()
+                                       .map(|r| r.to_string())
+                                       .collect::<Vec<String>>()
+                                       .join(",")
+                        , &self.verbosity
+                        , &self.status_line.to_string()
+                        , &self.trail.iter()
+                                     .map(|r| r.to_string())
+                                     .collect::<Vec<String>>()
+                                     .join(",")
+                        , &self.hints.join(",")
                        );
         s
     }
 
-    /// Allows the game state to be updated more easily.
-    /// Affects game behavior, player decisions, actions, etc.
-    /// Cannot set the current room with this function.
+    /// Whether the player can get through the given lock,
+    /// based on their progress and the keys they're carrying.
 
-    pub fn update(&self, change: (u8, bool)) -> State {
-        match change.0 {
-            1     =>
-                State {
-                    curr_room: self.curr_room,
-                    examined_wall: change.1,
-                    took_key: self.took_key,
-                    took_broom: self.took_broom,
-                    helped_carl: self.helped_carl,
-                    took_nail: self.took_nail,
-                    met_blimpo: self.met_blimpo,
-                    final_room_unlocked: self.final_room_unlocked,
-                    wearing_clothes: self.wearing_clothes,
-                },
-            2     =>
-                State {
-                    curr_room: self.curr_room,
-                    examined_wall: self.examined_wall,
-                    took_key: change.1,
-                    took_broom: self.took_broom,
-                    helped_carl: self.helped_carl,
-                    took_nail: self.took_nail,
-                    met_blimpo: self.met_blimpo,
-                    final_room_unlocked: self.final_room_unlocked,
-                    wearing_clothes: self.wearing_clothes,
-                },
-            3     =>
-                State {
-                    curr_room: self.curr_room,
-                    examined_wall: self.examined_wall,
-                    took_key: self.took_key,
-                    took_broom: change.1,
-                    helped_carl: self.helped_carl,
-                    took_nail: self.took_nail,
-                    met_blimpo: self.met_blimpo,
-                    final_room_unlocked: self.final_room_unlocked,
-                    wearing_clothes: self.wearing_clothes,
-                },
-            4     =>
-                State {
-                    curr_room: self.curr_room,
-                    examined_wall: self.examined_wall,
-                    took_key: self.took_key,
-                    took_broom: self.took_broom,
-                    helped_carl: change.1,
-                    took_nail: self.took_nail,
-                    met_blimpo: self.met_blimpo,
-                    final_room_unlocked: self.final_room_unlocked,
-                    wearing_clothes: self.wearing_clothes,
-                },
-            5     =>
-                State {
-                    curr_room: self.curr_room,
-                    examined_wall: self.examined_wall,
-                    took_key: self.took_key,
-                    took_broom: self.took_broom,
-                    helped_carl: self.helped_carl,
-                    took_nail: change.1,
-                    met_blimpo: self.met_blimpo,
-                    final_room_unlocked: self.final_room_unlocked,
-                    wearing_clothes: self.wearing_clothes,
-                },
-            6     =>
-                State {
-                    curr_room: self.curr_room,
-                    examined_wall: self.examined_wall,
-                    took_key: self.took_key,
-                    took_broom: self.took_broom,
-                    helped_carl: self.helped_carl,
-                    took_nail: self.took_nail,
-                    met_blimpo: change.1,
-                    final_room_unlocked: self.final_room_unlocked,
-                    wearing_clothes: self.wearing_clothes,
-                },
-            7     =>
-                State {
-                    curr_room: self.curr_room,
-                    examined_wall: self.examined_wall,
-                    took_key: self.took_key,
-                    took_broom: self.took_broom,
-                    helped_carl: self.helped_carl,
-                    took_nail: self.took_nail,
-                    met_blimpo: self.met_blimpo,
-                    final_room_unlocked: change.1,
-                    wearing_clothes: self.wearing_clothes,
-                },
-            8     =>
-                State {
-                    curr_room: self.curr_room,
-                    examined_wall: self.examined_wall,
-                    took_key: self.took_key,
-                    took_broom: self.took_broom,
-                    helped_carl: self.helped_carl,
-                    took_nail: self.took_nail,
-                    met_blimpo: self.met_blimpo,
-                    final_room_unlocked: self.final_room_unlocked,
-                    wearing_clothes: change.1,
-                },
-            _     =>
-                State {
-                    curr_room: self.curr_room,
-                    examined_wall: self.examined_wall,
-                    took_key: self.took_key,
-                    took_broom: self.took_broom,
-                    helped_carl: self.helped_carl,
-                    took_nail: self.took_nail,
-                    met_blimpo: self.met_blimpo,
-                    final_room_unlocked: self.final_room_unlocked,
-                    wearing_clothes: self.wearing_clothes,
-                },
+    pub fn has_opened(&self, lock: &str) -> bool {
+        match lock {
+            room::LOCK_KEY    => self.took_key,
+            room::LOCK_WALL   => self.examined_wall,
+            room::LOCK_STENCH => self.met_blimpo,
+            room::LOCK_BLIMPO => self.final_room_unlocked,
+            other             => self.keys.iter().any(|k| k == other),
         }
     }
-}
 
+    /// How many points the hints the player has taken cost them.
 
+    pub fn hint_penalty(&self) -> u32 {
+        self.hints.len() as u32 * HINT_COST
+    }
 
+    /// The player's score so far, less what their hints cost them.
 
+    pub fn current_score(&self) -> u32 {
+        self.score.saturating_sub(self.hint_penalty())
+    }
 
+    /// The player's score at the end of a run, with the escape
+    /// points if they got out, less what their hints cost them.
 
+    pub fn final_score(&self, escaped: bool) -> u32 {
+        let earned = if escaped { self.score + ESCAPE_POINTS } else { self.score };
+        earned.saturating_sub(self.hint_penalty())
+    }
 
+    /// Remembers that the player just left the given room, so they
+    /// can retrace the step. Only the latest MAX_TRAIL steps are kept.
 
+    pub fn step_from(mut self, room: usize) -> State {
+        self.trail.push(room);
+        if self.trail.len() > MAX_TRAIL {
+            self.trail.remove(0);
+        }
+        self
+    }
 
+    /// Advances the turn counter by one.
+    /// Called for each meaningful action the player takes.
 
+    pub fn tick(self) -> State {
+        State { turns: self.turns + 1, ..self }
+    }
 
+    /// The in-game time, derived from the turn counter,
+    /// formatted as a 12-hour clock.
 
+    pub fn clock(&self) -> String {
+        let minutes = (CLOCK_START_MINUTES + self.turns * MINUTES_PER_TURN)
+                      % (24 * 60);
+        let hour = match (minutes / 60) % 12 { 0 => 12, h => h };
+        let half = if minutes < 12 * 60 { "AM" } else { "PM" };
+        format!("{}:{:02} {}", hour, minutes % 60, half)
+    }
+
+    /// Allows the game state to be updated more easily.
+    /// Affects game behavior, player decisions, actions, etc.
+    /// Cannot set the current room with this function.
+    ///
+    /// Setting a milestone flag for the first time awards its points.
 
+    pub fn update(&self, change: (u8, bool)) -> State {
+        let already_set = self.flag(change.0);
+        let mut next = self.with_flag(change);
+        if change.1 && !already_set {
+            next.score += milestone_points(change.0);
+        }
+        next
+    }
 
+    /// Whether the flag with the given update() number is set.
+    /// Numbers that don't belong to a flag count as set.
 
+    pub fn flag(&self, number: u8) -> bool {
+        match number {
+            1 => self.examined_wall,
+            2 => self.took_key,
+            3 => self.took_broom,
+            4 => self.helped_carl,
+            5 => self.took_nail,
+            6 => self.met_blimpo,
+            7 => self.final_room_unlocked,
+            8 => self.wearing_clothes,
+            _ => true,
+        }
+    }
+
+    /// Sets the flag numbered change.0 to change.1,
+    /// using the same numbering as update().
+
+    fn with_flag(&self, change: (u8, bool)) -> State {
+        match change.0 {
+            1     => State { examined_wall: change.1, ..self.clone() },
+            2     => State { took_key: change.1, ..self.clone() },
+            3     => State { took_broom: change.1, ..self.clone() },
+            4     => State { helped_carl: change.1, ..self.clone() },
+            5     => State { took_nail: change.1, ..self.clone() },
+            6     => State { met_blimpo: change.1, ..self.clone() },
+            7     => State { final_room_unlocked: change.1, ..self.clone() },
+            8     => State { wearing_clothes: change.1, ..self.clone() },
+            _     => self.clone(),
+        }
+    }
+}
+
+#[test]
+fn update_awards_points_once_test() {
+    let start = State::empty();
+    let found = start.update((1, true));
+    assert_eq!(10, found.score);
+    // Setting the same flag again isn't worth anything.
+    assert_eq!(10, found.update((1, true)).score);
+    // Meeting Blimpo isn't a scoring milestone.
+    assert_eq!(10, found.update((6, true)).score);
+    // Every milestone, plus escaping, makes a perfect score.
+    let done = (1..=FLAGS.len() as u8).fold(start, |g, f| g.update((f, true)));
+    assert_eq!(MAX_SCORE, done.score + ESCAPE_POINTS);
+}
+
+#[test]
+fn clock_test() {
+    let mut gstate = State::empty();
+    assert_eq!("10:00 PM", gstate.clock());
+    gstate.turns = 25;
+    assert_eq!("12:05 AM", gstate.clock());
+}
+
+#[test]
+fn state_round_trip_test() {
+    let mut gstate = State::empty().update((1, true)).tick();
+    gstate.rng = Rng::new(99);
+    gstate.rng.range(0, 10);
+    let loaded = State::deserialize(&gstate.serialize());
+    assert!(loaded.examined_wall);
+    assert_eq!(1, loaded.turns);
+    assert_eq!(10, loaded.score);
+    assert_eq!(99, loaded.rng.seed);
+    assert_eq!(1, loaded.rng.draws);
+    // Events and their lasting effects survive too.
+    let mut later = loaded.clone();
+    later.fired.push("midnight".to_string());
+    later.room_texts.push((4, "../data/room/r4text1.txt".to_string()));
+    later.script_flags.push("read_note".to_string());
+    let reloaded = State::deserialize(&later.serialize());
+    assert_eq!(vec!["midnight".to_string()], reloaded.fired);
+    assert_eq!(later.room_texts, reloaded.room_texts);
+    assert_eq!(later.script_flags, reloaded.script_flags);
+    // And so do the rooms the player has seen.
+    let mut seen = reloaded.clone();
+    seen.visited = vec![0, 12];
+    seen.verbosity = "verbose".to_string();
+    seen.status_line = true;
+    seen.trail = vec![0];
+    seen.hints = vec!["took_key".to_string(), "took_key".to_string()];
+    let reloaded = State::deserialize(&seen.serialize());
+    assert_eq!(vec![0, 12], reloaded.visited);
+    assert_eq!(vec![0], reloaded.trail);
+    assert_eq!(seen.hints, reloaded.hints);
+    assert_eq!("verbose", reloaded.verbosity);
+    assert!(reloaded.status_line);
+}
+
+#[test]
+fn final_score_test() {
+    let mut gstate = State::empty();
+    gstate.score = 30;
+    gstate.hints = vec!["took_key".to_string(); 21];
+    // Hints cost more than the milestones were worth,
+    // which the escape points still have to make up for.
+    assert_eq!(0, gstate.current_score());
+    assert_eq!(13, gstate.final_score(true));
+    assert_eq!(0, gstate.final_score(false));
+}
+
+#[test]
+fn step_from_test() {
+    let mut gstate = State::empty();
+    for room in 0..MAX_TRAIL + 10 {
+        gstate = gstate.step_from(room);
+    }
+    // The oldest steps are forgotten.
+    assert_eq!(MAX_TRAIL, gstate.trail.len());
+    assert_eq!(Some(&10), gstate.trail.first());
+}
+
+#[test]
+fn wing_round_trip_test() {
+    let mut gstate = wing::new_game(State::empty());
+    gstate.keys.push("brass".to_string());
+    let loaded = State::deserialize(&gstate.serialize());
+    assert_eq!("wing", loaded.mode);
+    assert_eq!(gstate.map.descs, loaded.map.descs);
+    assert!(loaded.has_opened("brass"));
+    assert!(!loaded.has_opened("iron"));
+}
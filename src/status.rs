@@ -0,0 +1,144 @@
+use crate::state::{State, MAX_HEALTH};
+
+// Names of the status effects the player can suffer from.
+pub const NAUSEATED: &str = "nauseated";
+pub const EXHAUSTED: &str = "exhausted";
+pub const INJURED: &str   = "injured";
+
+/// Represents a status effect on the player,
+/// which wears off once the given turn comes around.
+
+#[derive(Clone)]
+pub struct Effect {
+    pub name: String,
+    pub until: u32,
+}
+
+/// How many turns each status effect lasts.
+
+fn duration(name: &str) -> u32 {
+    match name {
+        NAUSEATED => 4,
+        EXHAUSTED => 2,
+        INJURED   => 8,
+        _         => 0,
+    }
+}
+
+/// Whether the player is currently suffering from the given effect.
+
+pub fn has(gstate: &State, name: &str) -> bool {
+    gstate.effects.iter().any(|e| e.name == name)
+}
+
+/// Puts the given effect on the player, starting this turn.
+/// An effect the player already has lasts its full duration again.
+
+pub fn add(gstate: State, name: &str) -> State {
+    let until = gstate.turns + duration(name);
+    let mut effects: Vec<Effect> = gstate.effects.iter()
+                                                 .filter(|e| e.name != name)
+                                                 .cloned()
+                                                 .collect();
+    effects.push(Effect { name: name.to_string(), until });
+    State { effects, ..gstate }
+}
+
+/// Called whenever a turn passes. Removes every effect
+/// that has worn off, letting the player know.
+
+pub fn wear_off(gstate: State) -> State {
+    for effect in gstate.effects.iter().filter(|e| e.until <= gstate.turns) {
+        match effect.name.as_str() {
+            NAUSEATED => println!("\n\nYour stomach finally settles."),
+            EXHAUSTED => println!("\n\nYou've caught your breath."),
+            INJURED   => println!("\n\nYour wounds stop throbbing."),
+            _         => {},
+        }
+    }
+    let effects = gstate.effects.iter()
+                                .filter(|e| e.until > gstate.turns)
+                                .cloned()
+                                .collect();
+    State { effects, ..gstate }
+}
+
+/// Prints a line about each effect the player is suffering
+/// from, meant to follow the current room's description.
+
+pub fn describe(gstate: &State) {
+    if has(gstate, NAUSEATED) {
+        println!("The stench still clings to you. Your stomach churns.");
+    }
+    if has(gstate, EXHAUSTED) {
+        println!("You're breathing hard, too worn out to go anywhere.");
+    }
+    if has(gstate, INJURED) {
+        println!("Your wounds throb with every heartbeat.");
+    }
+}
+
+/// Displays the player's health and any effects they're
+/// suffering from, along with how long each will last.
+
+pub fn status_cmd(gstate: &State) {
+    println!("\n\nHealth: {} of {}", gstate.health, MAX_HEALTH);
+    if gstate.effects.is_empty() {
+        println!("You feel fine.");
+    }
+    for effect in gstate.effects.iter() {
+        // A script can set an effect to end on a turn already past,
+        // in which case it wears off once the next turn passes.
+        let left = effect.until.saturating_sub(gstate.turns);
+        println!("You are {} ({} more turn{}).",
+                 effect.name, left, if left == 1 { "" } else { "s" });
+    }
+}
+
+/// Converts the player's effects into a single
+/// comma separated line for saving.
+
+pub fn serialize(effects: &[Effect]) -> String {
+    effects.iter()
+           .map(|e| format!("{}:{}", e.name, e.until))
+           .collect::<Vec<String>>()
+           .join(",")
+}
+
+/// Reads the player's effects back from a saved line.
+
+pub fn deserialize(data: &str) -> Vec<Effect> {
+    data.split(',')
+        .filter(|e| !e.is_empty())
+        .map(|e| {
+            let mut parts = e.split(':');
+            Effect {
+                name: parts.next().unwrap().to_string(),
+                until: parts.next().unwrap().parse().unwrap(),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn effects_wear_off_test() {
+    let gstate = add(State::empty(), NAUSEATED);
+    assert!(has(&gstate, NAUSEATED));
+    let mut later = gstate.clone();
+    later.turns = 3;
+    assert!(has(&wear_off(later.clone()), NAUSEATED));
+    later.turns = 4;
+    assert!(!has(&wear_off(later), NAUSEATED));
+    // Effects survive a save and load.
+    let loaded = deserialize(&serialize(&gstate.effects));
+    assert_eq!(NAUSEATED, loaded[0].name);
+    assert_eq!(4, loaded[0].until);
+}
+
+#[test]
+fn status_cmd_test_overdue() {
+    let mut gstate = add(State::empty(), EXHAUSTED);
+    gstate.turns = 5;
+    // An effect past its end shows no turns left, rather than panicking.
+    status_cmd(&gstate);
+}
@@ -0,0 +1,266 @@
+use crate::rng::Rng;
+use crate::room::{self, Exit, Map};
+use crate::state::State;
+
+// Generated rooms are numbered from here up,
+// so they never get mistaken for the classic jail's rooms.
+pub const WING_START: usize = 100;
+
+// Names of the keys that can be found in a wing, in the order
+// their doors appear between the starting cell and the exit.
+const KEY_NAMES: [&str; 2] = ["brass", "iron"];
+
+// Room templates, by the kind of room they describe.
+const CELL_TEMPLATES: [&str; 3] = [
+    "You are in a bare cell, barely big enough to lie down in.",
+    "You are in an empty cell.\nSomeone has scratched \
+     tally marks into every inch of the wall.",
+    "You are in a cell with a rusted cot and nothing else.",
+];
+const CORRIDOR_TEMPLATES: [&str; 3] = [
+    "You are in a stretch of corridor, lit by a single flickering bulb.",
+    "You are in a damp corridor.\nWater drips somewhere in the dark.",
+    "You are at a bend in the corridor. The paint is peeling badly.",
+];
+const STORAGE_TEMPLATES: [&str; 2] = [
+    "You are in a cramped storage room,\nits shelves crowded \
+     with buckets and rags.",
+    "You are in a supply closet that reeks of bleach.",
+];
+const START_TEMPLATE: &str =
+    "You are in a cramped cell, in a wing of the jail\n\
+     you don't recognize at all.";
+const EXIT_TEMPLATE: &str =
+    "You are at the end of a hallway.\nA heavy door \
+     marked \"EXIT\" stands at its northern end.";
+
+// Each direction a room can connect in, with the grid step it takes
+// and the direction leading back.
+const DIRECTIONS: [(&str, i32, i32, &str); 4] = [
+    ("north", 0, 1, "south"),
+    ("east", 1, 0, "west"),
+    ("south", 0, -1, "north"),
+    ("west", -1, 0, "east"),
+];
+
+/// Builds a new, connected prison wing from the given seed.
+/// Rooms are laid out on a grid, branching out from the starting
+/// cell, and the room furthest from it gets the exit. Locked doors
+/// on the way out have their keys placed where the player can
+/// get to them first, so every wing can be escaped from.
+
+pub fn generate(seed: u64) -> Map {
+    let mut rng = Rng::new(seed);
+    let count = rng.range(8, 13) as usize;
+    // Grid position and the room each one branched out from.
+    let mut grid: Vec<(i32, i32)> = vec![(0, 0)];
+    let mut parent: Vec<usize> = vec![0];
+    let mut exits: Vec<Exit> = Vec::new();
+    while grid.len() < count {
+        let from = rng.range(0, grid.len() as u32) as usize;
+        let (dir, dx, dy, back) = DIRECTIONS[rng.range(0, 4) as usize];
+        let pos = (grid[from].0 + dx, grid[from].1 + dy);
+        if grid.contains(&pos) {
+            continue;
+        }
+        let to = grid.len();
+        grid.push(pos);
+        parent.push(from);
+        exits.push(passage(WING_START + from, dir, WING_START + to));
+        exits.push(passage(WING_START + to, back, WING_START + from));
+    }
+    // The exit is in the deepest room with nothing built north of it.
+    let depth = |mut r: usize| { let mut d = 0;
+                                 while r != 0 { r = parent[r]; d += 1; }
+                                 d };
+    let exit_room = (1..grid.len())
+        .filter(|&r| !grid.contains(&(grid[r].0, grid[r].1 + 1)))
+        .max_by_key(|&r| (depth(r), r))
+        .unwrap_or(0);
+    exits.push(Exit {
+        from: WING_START + exit_room,
+        dir: "north".to_string(),
+        to: room::OUTSIDE,
+        lock: String::new(),
+        message: "You push the heavy door open.".to_string(),
+        blocked: String::new(),
+    });
+    // Lock doors along the way out, starting from the exit's end.
+    let mut path = vec![exit_room];
+    while *path.last().unwrap() != 0 {
+        let next = parent[*path.last().unwrap()];
+        path.push(next);
+    }
+    path.reverse();
+    let mut keys = Vec::new();
+    let locks = KEY_NAMES.len().min(path.len() - 1);
+    let mut lock_at: Vec<usize> = Vec::new();
+    while lock_at.len() < locks {
+        let step = rng.range(0, path.len() as u32 - 1) as usize;
+        if !lock_at.contains(&step) {
+            lock_at.push(step);
+        }
+    }
+    lock_at.sort();
+    for (i, &step) in lock_at.iter().enumerate() {
+        let (a, b) = (WING_START + path[step], WING_START + path[step + 1]);
+        for exit in exits.iter_mut()
+                         .filter(|e| (e.from == a && e.to == b)
+                                     || (e.from == b && e.to == a)) {
+            exit.lock = KEY_NAMES[i].to_string();
+            exit.blocked = format!("The door to the {} is locked.\n\
+                                    It has a {} keyhole.",
+                                   exit.dir, KEY_NAMES[i]);
+        }
+        // The key goes somewhere the player can already get to.
+        let partial = Map { start: WING_START, exits: exits.clone(),
+                            descs: Vec::new(), names: Vec::new(),
+                            coords: Vec::new(), keys: Vec::new() };
+        let held = &KEY_NAMES[..i];
+        let region: Vec<usize> = partial.reachable(&|lock| held.contains(&lock))
+                                        .into_iter()
+                                        .filter(|&r| r != room::OUTSIDE)
+                                        .collect();
+        let spot = region[rng.range(0, region.len() as u32) as usize];
+        keys.push((KEY_NAMES[i].to_string(), spot));
+    }
+    // Describe every room now that its exits are settled.
+    let mut descs = Vec::new();
+    let mut names = Vec::new();
+    for r in 0..grid.len() {
+        let index = WING_START + r;
+        let doors = exits.iter().filter(|e| e.from == index).count();
+        let (name, template) = if r == 0 {
+            ("Strange Cell", START_TEMPLATE)
+        } else if r == exit_room {
+            ("Exit Hallway", EXIT_TEMPLATE)
+        } else if doors == 1 && rng.range(0, 2) == 0 {
+            ("Storage Room", STORAGE_TEMPLATES[rng.range(0, 2) as usize])
+        } else if doors == 1 {
+            ("Empty Cell", CELL_TEMPLATES[rng.range(0, 3) as usize])
+        } else {
+            ("Corridor", CORRIDOR_TEMPLATES[rng.range(0, 3) as usize])
+        };
+        descs.push((index, format!("{}\n{}", template,
+                                   describe_exits(&exits, index))));
+        names.push((index, name.to_string()));
+    }
+    let coords = grid.iter()
+                     .enumerate()
+                     .map(|(r, &pos)| (WING_START + r, pos))
+                     .collect();
+    let map = Map { start: WING_START, exits, descs, names, coords, keys };
+    debug_assert!(is_solvable(&map));
+    map
+}
+
+/// An open passage between two generated rooms.
+
+fn passage(from: usize, dir: &str, to: usize) -> Exit {
+    Exit {
+        from,
+        dir: dir.to_string(),
+        to,
+        lock: String::new(),
+        message: format!("You head {}.", dir),
+        blocked: String::new(),
+    }
+}
+
+/// Lists the ways out of a generated room, mentioning any locks.
+
+fn describe_exits(exits: &[Exit], index: usize) -> String {
+    let mut lines = Vec::new();
+    for exit in exits.iter().filter(|e| e.from == index
+                                        && e.to != room::OUTSIDE) {
+        if exit.lock.is_empty() {
+            lines.push(format!("A passage leads {}.", exit.dir));
+        } else {
+            lines.push(format!("A door with a {} lock leads {}.",
+                               exit.lock, exit.dir));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Checks that a map can be escaped from, by collecting every
+/// key the player could reach until no more doors open up.
+
+pub fn is_solvable(map: &Map) -> bool {
+    let mut held: Vec<String> = Vec::new();
+    loop {
+        let rooms = map.reachable(&|lock| held.iter().any(|k| k == lock));
+        let before = held.len();
+        for (name, spot) in map.keys.iter() {
+            if rooms.contains(spot) && !held.contains(name) {
+                held.push(name.clone());
+            }
+        }
+        if held.len() == before {
+            return rooms.contains(&room::OUTSIDE)
+        }
+    }
+}
+
+/// Starts a game in a freshly generated wing, seeded from
+/// the game's rng so the same seed builds the same wing.
+
+pub fn new_game(mut gstate: State) -> State {
+    let map_seed = gstate.rng.next_u64();
+    let map = generate(map_seed);
+    State {
+        mode: "wing".to_string(),
+        curr_room: map.start,
+        map_seed,
+        map,
+        ..gstate
+    }
+}
+
+/// The name of a key lying in the player's room that matches what
+/// they asked for, either by its own name or just as "key".
+
+pub fn key_here(gstate: &State, obj: &str) -> Option<String> {
+    gstate.map.keys.iter()
+                   .find(|k| k.1 == gstate.curr_room
+                             && !gstate.keys.contains(&k.0)
+                             && (obj == k.0 || obj == "key"))
+                   .map(|k| k.0.clone())
+}
+
+/// Prints a line about each key lying in the player's room.
+/// Meant to follow the room description.
+
+pub fn describe_keys(gstate: &State) {
+    for (name, _) in gstate.map.keys.iter()
+                                    .filter(|k| k.1 == gstate.curr_room
+                                                && !gstate.keys.contains(&k.0)) {
+        println!("A {} key lies on the floor.", name);
+    }
+}
+
+#[test]
+fn generate_test() {
+    for seed in 0..200 {
+        let map = generate(seed);
+        assert!(is_solvable(&map));
+        assert!(map.descs.len() >= 8);
+        // Every passage between rooms can be walked back through.
+        for exit in map.exits.iter().filter(|e| e.to != room::OUTSIDE) {
+            assert!(map.direction_to(exit.to, exit.from).is_some());
+        }
+    }
+    // The same seed always builds the same wing.
+    assert_eq!(generate(9).descs, generate(9).descs);
+}
+
+#[test]
+fn key_here_test() {
+    let mut gstate = new_game(State::empty());
+    let (name, spot) = gstate.map.keys[0].clone();
+    gstate.curr_room = spot;
+    assert_eq!(Some(name.clone()), key_here(&gstate, "key"));
+    assert_eq!(Some(name.clone()), key_here(&gstate, &name));
+    gstate.keys.push(name.clone());
+    assert_eq!(None, key_here(&gstate, &name));
+}